@@ -0,0 +1,23 @@
+// Starting hole solvability report, for every board from 3 lines to N lines, as Markdown or CSV.
+// cargo run --release --bin report -- 5 markdown > results.md
+// cargo run --release --bin report -- 5 csv > results.csv
+
+use triangle_problem::dyn_pyra::report::{solvability_report, ReportFormat, MAX_REPORT_LINES};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let max_lines: usize = args.next().map_or(Some(4), |arg| arg.parse().ok())
+        .filter(|lines| (3..=MAX_REPORT_LINES).contains(lines))
+        .unwrap_or_else(|| usage());
+    let format = match args.next().as_deref() {
+        None | Some("markdown") | Some("md") => ReportFormat::Markdown,
+        Some("csv") => ReportFormat::Csv,
+        Some(_) => usage(),
+    };
+    print!("{}", solvability_report(max_lines, format));
+}
+
+fn usage() -> ! {
+    eprintln!("usage : report [lines 3..={}] [markdown|csv]", MAX_REPORT_LINES);
+    std::process::exit(2)
+}
@@ -0,0 +1,13 @@
+// HTTP/JSON API over dyn_pyra, see src/server.rs for the endpoints.
+// cargo run --release --features server --bin server -- 127.0.0.1:8080
+
+use tiny_http::Server;
+
+use triangle_problem::server::serve;
+
+fn main() {
+    let addr = std::env::args().nth(1).unwrap_or("127.0.0.1:8080".to_string());
+    let server = Server::http(&addr).expect("address available");
+    println!("Listening on http://{}", addr);
+    serve(server);
+}
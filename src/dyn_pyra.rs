@@ -1,8 +1,37 @@
 use std::hash::{Hash, Hasher};
+use std::time::Duration;
 
 use indicatif::{MultiProgress, ProgressBar, ProgressIterator};
 use petgraph::{algo::connected_components, prelude::GraphMap, Undirected};
 
+pub mod animation;
+pub mod beam;
+pub mod checkpoint;
+pub mod classes;
+pub mod deepening;
+pub mod display;
+pub mod external_bfs;
+pub mod grundy;
+pub mod nested;
+pub mod pagoda;
+pub mod paths;
+pub mod playouts;
+pub mod puzzle;
+pub mod record;
+pub mod region_solver;
+pub mod regions;
+pub mod report;
+pub mod retrograde;
+pub mod seed_array;
+pub mod solver;
+pub mod state_graph;
+pub mod svg;
+pub mod tablebase;
+pub mod traces;
+pub mod two_player;
+use checkpoint::Checkpoint;
+use state_graph::StateGraph;
+
 pub fn test_dyn_graph(rules: PyramideRules) {
     println!("Using build_full_graph method");
     let full_graph = build_full_graph(rules.lines, rules.diag_allowed);
@@ -21,6 +50,16 @@ pub fn test_dyn_graph(rules: PyramideRules) {
     println!("Number of components : {:?}", nb_comp_partial);
 }
 
+pub fn test_state_graph(rules: PyramideRules) {
+    println!("Using StateGraph::build_full_resumable method");
+    let mut checkpoint = Checkpoint::new("checkpoints", rules, Duration::from_secs(60));
+    let full_graph = StateGraph::build_full_resumable(&mut checkpoint).expect("checkpoint readable");
+    let nb_comp = connected_components(&full_graph);
+    println!("Number of nodes : {:?}", full_graph.node_count());
+    println!("Number of edges : {:?}", full_graph.edge_count());
+    println!("Number of components : {:?}", nb_comp);
+}
+
 pub fn build_full_graph(lines: usize, diag_allowed: bool) -> GraphMap::<Seed, Coup, Undirected> {
     let mut state_graph = GraphMap::<Seed, Coup, Undirected>::new();
     let temp_pyra = Pyramide::new(lines, diag_allowed);
@@ -53,7 +92,7 @@ fn build_partial_graph(mut to_visit: Vec<Pyramide>) -> GraphMap::<Seed, Coup, Un
     let mult_prog = MultiProgress::new();
     let bar = mult_prog.add(ProgressBar::new((to_visit[0].lines.pow(2)-2) as u64));
     mult_prog.println("starting!").unwrap();
-    while to_visit.len() != 0 {
+    while !to_visit.is_empty() {
         bar.set_position(state_graph.node_count() as u64);
         let bar2 = mult_prog.add(ProgressBar::new(to_visit.len() as u64));
         for pyra in to_visit.drain(..).progress_with(bar2) {
@@ -75,17 +114,21 @@ fn build_partial_graph(mut to_visit: Vec<Pyramide>) -> GraphMap::<Seed, Coup, Un
                 state_graph.add_edge(pyra_seed, new_pyra_seed, *coup_pos);
             }
         }
-        to_visit.extend(new_to_visit.drain(..));
+        to_visit.append(&mut new_to_visit);
     }
     bar.finish();
     return state_graph
 }
 
 
-fn cherche_coups_possibles(pyramide: &Pyramide, coups: &Vec<Coup>) -> Option<Vec<Coup>> {
+fn cherche_coups_inverses(pyramide: &Pyramide, coups: &[Coup]) -> Vec<Coup> {
+    coups.iter().filter(|x| pyramide.is_coup_inverse_valid(x)).copied().collect()
+}
+
+pub fn cherche_coups_possibles(pyramide: &Pyramide, coups: &[Coup]) -> Option<Vec<Coup>> {
     let res: Vec<Coup> = coups.iter()
-        .filter_map(|x| (pyramide.is_coup_valid(x)).then(|| *x)).collect();
-    if res.len() > 0 {
+        .filter(|x| pyramide.is_coup_valid(x)).copied().collect();
+    if !res.is_empty() {
         return Some(res)
     } else {
         return None
@@ -119,13 +162,13 @@ pub enum Direction {
     Bas
 }
 
-type Seed = u128;
+pub type Seed = u128;
 // This size allows for the graph resolutions to be computed with a pyramide of lines : 11
 // to solve larger boards, a bigger seed representation needs to be implemented (the code is most likely too slow to handle such size)
 // or change the way to generate boards
 
 type Cell = Option<bool>;
-type CellIndex = usize;
+pub type CellIndex = usize;
 
 #[derive(Debug, Clone, PartialOrd, Eq, Ord)]
 pub struct Pyramide {
@@ -137,11 +180,11 @@ pub struct Pyramide {
 }    
 
 impl Pyramide {
-    fn new(lines: usize, diag_allowed: bool) -> Pyramide {
-        Pyramide { lines, cells: vec![None; (lines+1)*(2*lines+1)], diag_allowed: diag_allowed }
+    pub fn new(lines: usize, diag_allowed: bool) -> Pyramide {
+        Pyramide { lines, cells: vec![None; (lines+1)*(2*lines+1)], diag_allowed }
     }
 
-    fn init_from_seed(lines: usize, seed: Seed, diag_allowed: bool) -> Pyramide {
+    pub fn init_from_seed(lines: usize, seed: Seed, diag_allowed: bool) -> Pyramide {
         let mut pyra = Pyramide::new(lines, diag_allowed);
 
         let cells_num = lines*lines;
@@ -163,7 +206,7 @@ impl Pyramide {
         return pyra
     }
 
-    fn init_full(lines: usize, diag_allowed: bool) -> Pyramide {
+    pub fn init_full(lines: usize, diag_allowed: bool) -> Pyramide {
         return Pyramide::init_from_seed(
             lines,
             2_u128.pow((lines*lines).try_into().unwrap())-1,
@@ -171,26 +214,43 @@ impl Pyramide {
         )
     }
 
-    fn partie_gagne(&self) -> bool {
+    pub fn partie_gagne(&self) -> bool {
         self.cells.iter().filter(|x| *x == &Some(true)).count() == 1
     }
 
+    #[allow(dead_code)]
     fn update_cell(&mut self, idx: CellIndex, value: Cell) -> Result<(),()> {
-        let vec_idx = match self.cell_index_to_vec_index(idx) {
-            Ok(vec_idx) => vec_idx,
-            Err(e) => return Err(e)
-        };
+        let vec_idx = self.cell_index_to_vec_index(idx)?;
         self.cells[vec_idx] = value;
         Ok(())
     }
 
-    fn coup(&mut self, coup: &Coup) -> Result<(),()> {
-        if !self.is_coup_valid(&coup) {
+    pub fn coup(&mut self, coup: &Coup) -> Result<(),()> {
+        if !self.is_coup_valid(coup) {
             return Err(())
         };
-        let mil_vec_idx = self.cell_index_to_vec_index(coup.mil).expect("Already checked");
-        
-        _ = self.cells[mil_vec_idx].replace(false);
+        let (dep_idx, mil_idx, arr_idx) = self.coup_vec_indexes(coup).expect("Already checked");
+        _ = self.cells[dep_idx].replace(false);
+        _ = self.cells[mil_idx].replace(false);
+        _ = self.cells[arr_idx].replace(true);
+        Ok(())
+    }
+
+    pub fn coup_inverse(&mut self, coup: &Coup) -> Result<(),()> {
+        // un-jump : the peg goes back to its departure cell and the jumped peg is put back
+        if !self.is_coup_inverse_valid(coup) {
+            return Err(())
+        };
+        let (dep_idx, mil_idx, arr_idx) = self.coup_vec_indexes(coup).expect("Already checked");
+        _ = self.cells[dep_idx].replace(true);
+        _ = self.cells[mil_idx].replace(true);
+        _ = self.cells[arr_idx].replace(false);
+        Ok(())
+    }
+
+    fn coup_vec_indexes(&self, coup: &Coup) -> Result<(usize, usize, usize), ()> {
+        // departure, middle and arrival indexes in self.cells
+        let mil_vec_idx = self.cell_index_to_vec_index(coup.mil)?;
 
         let max_line = self.lines * 2 + 1;
         let top_row = mil_vec_idx - max_line;
@@ -207,9 +267,30 @@ impl Pyramide {
             (Orientation::Horizontal, Direction::Bas) => (mid_row-1, mid_row+1),
             (Orientation::Horizontal, Direction::Haut) => (mid_row+1, mid_row-1),
         };
-        _ = self.cells[dep_idx].replace(false);
-        _ = self.cells[arr_idx].replace(true);
-        Ok(())
+        Ok((dep_idx, mid_row, arr_idx))
+    }
+
+    pub fn is_coup_inverse_valid(&self, coup: &Coup) -> bool {
+        let diagonal = matches!(coup.orientation, Orientation::DiagonalG | Orientation::DiagonalD);
+        if diagonal && !self.diag_allowed {
+            return false
+        }
+        match self.coup_vec_indexes(coup) {
+            Ok((dep_idx, mil_idx, arr_idx)) =>
+                [self.cells[dep_idx], self.cells[mil_idx], self.cells[arr_idx]] == [Some(false), Some(false), Some(true)],
+            Err(_) => false
+        }
+    }
+
+    fn cell_position(&self, idx: CellIndex) -> (usize, usize) {
+        // (row, column) of a cell, the column going from 0 to 2*lines-2 like the naive board
+        let row = idx.isqrt();
+        (row, self.lines - 1 - row + idx - row*row)
+    }
+
+    pub fn cell_name(&self, idx: CellIndex) -> String {
+        let (row, col) = self.cell_position(idx);
+        format!("{}{}", (b'a' + row as u8) as char, col + 1)
     }
 
     fn cell_index_to_vec_index(&self, idx: CellIndex) -> Result<usize, ()> {
@@ -229,11 +310,48 @@ impl Pyramide {
         Err(())
     }
 
-    pub fn iter(&self) -> CellsIterator {
+    fn vec_index_to_cell_index(&self, vec_idx: usize) -> Result<CellIndex, ()> {
+        let max_line = self.lines * 2 + 1;
+        let (row, col) = (vec_idx / max_line, vec_idx % max_line);
+        if row >= self.lines || col < self.lines - row || col > self.lines + row {
+            return Err(())
+        }
+        Ok(row*row + col - (self.lines - row))
+    }
+
+    pub fn coup_name(&self, coup: &Coup) -> Option<String> {
+        // departure and arrival cells, like "c4-a4"
+        let [dep, _, arr] = self.coup_cells(coup)?;
+        Some(format!("{}-{}", self.cell_name(dep), self.cell_name(arr)))
+    }
+
+    pub fn coup_from_name(&self, name: &str) -> Option<Coup> {
+        // inverse of coup_name
+        coups_theoriques(self).into_iter().find(|coup| self.coup_name(coup).as_deref() == Some(name))
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        PyramideRules { lines: self.lines, diag_allowed: self.diag_allowed }
+    }
+
+    pub fn coup_cells(&self, coup: &Coup) -> Option<[CellIndex; 3]> {
+        // departure, middle and arrival cells, None if the move goes out of the board
+        let (dep_idx, mil_idx, arr_idx) = self.coup_vec_indexes(coup).ok()?;
+        if !self.diag_allowed && matches!(coup.orientation, Orientation::DiagonalG | Orientation::DiagonalD) {
+            return None
+        }
+        Some([
+            self.vec_index_to_cell_index(dep_idx).ok()?,
+            self.vec_index_to_cell_index(mil_idx).ok()?,
+            self.vec_index_to_cell_index(arr_idx).ok()?,
+        ])
+    }
+
+    pub fn iter(&self) -> CellsIterator<'_> {
         CellsIterator { cells: &self.cells, lines: self.lines, index: 0, col_index: 0, cur_line: 0 }
     }
 
-    pub fn move_iter(&self) -> CellsIterator {
+    pub fn move_iter(&self) -> CellsIterator<'_> {
         CellsIterator { cells: &self.cells, lines: self.lines, index: 1, col_index: 0, cur_line: 1 }
     }
 
@@ -299,31 +417,14 @@ impl Pyramide {
     }
 
     pub fn print(&self) {
-        let mut first_line = " ".to_string();
-        for i in 1..=self.lines*2-1 {
-            first_line.push_str(format!(" {}", i).as_str());
-        }
-        // println!("  1 2 3 4 5 6 7");
-        for l in self.cells.chunks(self.lines*2-1) {
+        println!("{}", self);
+    }
 
-        }
-        // println!("a       {}      ", self.a[0] as u8);
-        // let b: Vec<u8> = self.b.clone().into_iter().map(|x| x as u8).collect();
-        // println!("b     {} {} {}   ", b[0], b[1], b[2]);
-        // let c: Vec<u8> = self.c.clone().into_iter().map(|x| x as u8).collect();
-        // println!("c   {} {} {} {} {}", c[0], c[1], c[2], c[3], c[4]);
-        // let d: Vec<u8> = self.d.clone().into_iter().map(|x| x as u8).collect();
-        // println!(
-        //     "d {} {} {} {} {} {} {}",
-        //     d[0], d[1], d[2], d[3], d[4], d[5], d[6],
-        // );
-        println!();
-    }
-
-    fn seed(&self) -> u128 {
+    pub fn seed(&self) -> u128 {
         self.iter()
             .enumerate()
-            .filter_map(|(i, cell)| (cell == &Some(true)).then(|| 1 << i))
+            .filter(|(_, cell)| *cell == &Some(true))
+            .map(|(i, _)| 1 << i)
             .sum()
     }
 }
@@ -341,7 +442,7 @@ impl PartialEq for Pyramide {
     }
 }
 
-fn coups_theoriques(pyra: &Pyramide) -> Vec<Coup> {
+pub fn coups_theoriques(pyra: &Pyramide) -> Vec<Coup> {
     let mut res = Vec::new();
     let max_pos = (pyra.lines)*(pyra.lines);
     let orients = if pyra.diag_allowed {
@@ -361,6 +462,24 @@ fn coups_theoriques(pyra: &Pyramide) -> Vec<Coup> {
     return res
 }
 
+pub fn jumps(rules: PyramideRules) -> Vec<(Coup, [CellIndex; 3])> {
+    // every move of the rules that stays on the board, with its departure, middle and arrival cells
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    coups_theoriques(&pyra).into_iter()
+        .filter_map(|coup| pyra.coup_cells(&coup).map(|cells| (coup, cells)))
+        .collect()
+}
+
+pub fn jump_partners(rules: PyramideRules) -> Vec<Seed> {
+    // partners[cell] : cells forming a (departure, middle) pair with cell
+    let mut partners = vec![0; rules.lines * rules.lines];
+    for (_, [dep, mil, _]) in jumps(rules) {
+        partners[dep] |= 1 << mil;
+        partners[mil] |= 1 << dep;
+    }
+    partners
+}
+
 pub struct CellsIterator<'a> {
     cells: &'a Vec<Cell>,
     lines: usize,
@@ -586,4 +705,80 @@ mod tests {
         assert!(res == Ok(()));
         assert_eq!(pyra.seed(), 64);
     }
+
+    #[test]
+    fn test_coup_inverse() {
+        // _ _ F            _ _ T
+        // _ F F F     ->   _ F T F
+        // F F T F F        F F F F F
+        // seed : 64
+        let mut pyra = Pyramide::init_from_seed(3, 64, false);
+        let coups = coups_theoriques(&pyra);
+        let inverses = cherche_coups_inverses(&pyra, &coups);
+        assert_eq!(inverses.len(), 3);
+        let coup = Coup {mil: 2, orientation: Orientation::Vertical, dir: Direction::Bas};
+        assert!(inverses.contains(&coup));
+        assert!(inverses.contains(&Coup {mil: 5, orientation: Orientation::Horizontal, dir: Direction::Bas}));
+        assert!(inverses.contains(&Coup {mil: 7, orientation: Orientation::Horizontal, dir: Direction::Haut}));
+        assert!(pyra.coup_inverse(&coup) == Ok(()));
+        assert_eq!(pyra.seed(), 5);
+        assert!(pyra.coup_inverse(&coup).is_err());
+
+        // every inverse move is undone by the corresponding move
+        for seed in 1..512 {
+            let pyra = Pyramide::init_from_seed(3, seed, true);
+            for coup in cherche_coups_inverses(&pyra, &coups_theoriques(&pyra)) {
+                let mut prev = pyra.clone();
+                prev.coup_inverse(&coup).expect("valid");
+                assert!(prev.is_coup_valid(&coup));
+                prev.coup(&coup).expect("valid");
+                assert_eq!(prev, pyra);
+            }
+        }
+    }
+
+    #[test]
+    fn test_coup_cells() {
+        let pyra = Pyramide::init_full(4, true);
+        for idx in 0..16 {
+            let vec_idx = pyra.cell_index_to_vec_index(idx).expect("on the board");
+            assert_eq!(pyra.vec_index_to_cell_index(vec_idx), Ok(idx));
+        }
+        assert_eq!(pyra.vec_index_to_cell_index(0), Err(()));
+        let coup = Coup {mil: 2, orientation: Orientation::Vertical, dir: Direction::Haut};
+        assert_eq!(pyra.coup_cells(&coup), Some([6, 2, 0]));
+        let coup = Coup {mil: 1, orientation: Orientation::Horizontal, dir: Direction::Bas};
+        assert_eq!(pyra.coup_cells(&coup), None);
+        let coup = Coup {mil: 5, orientation: Orientation::DiagonalD, dir: Direction::Bas};
+        assert_eq!(pyra.coup_cells(&coup), Some([2, 5, 10]));
+        assert_eq!(Pyramide::init_full(4, false).coup_cells(&coup), None);
+        assert_eq!(pyra.coup_name(&coup), Some("b4-d2".to_string()));
+        // a move is valid on the full board minus its arrival cell exactly when it stays on the board
+        for coup in coups_theoriques(&pyra) {
+            if let Some([_, _, arr]) = pyra.coup_cells(&coup) {
+                let hole = Pyramide::init_from_seed(4, 0xFFFF & !(1 << arr), true);
+                assert!(hole.is_coup_valid(&coup));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_name() {
+        let pyra = Pyramide::init_full(4, false);
+        let names: Vec<String> = (0..16).map(|idx| pyra.cell_name(idx)).collect();
+        assert_eq!(names, ["a4", "b3", "b4", "b5", "c2", "c3", "c4", "c5", "c6",
+                           "d1", "d2", "d3", "d4", "d5", "d6", "d7"]);
+    }
+
+    #[test]
+    fn test_coup_from_name() {
+        let pyra = Pyramide::init_full(4, true);
+        for coup in coups_theoriques(&pyra) {
+            if let Some(name) = pyra.coup_name(&coup) {
+                assert_eq!(pyra.coup_from_name(&name), Some(coup));
+            }
+        }
+        assert_eq!(pyra.coup_from_name("a4-a6"), None);
+        assert_eq!(Pyramide::init_full(4, false).coup_from_name("a4-c2"), None);
+    }
 }
\ No newline at end of file
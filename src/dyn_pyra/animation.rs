@@ -0,0 +1,180 @@
+// Animated GIF of a game, one frame per jump. Each frame shows the board before the jump with the
+// same highlighting as the SVG solution sheets, the last frame shows the final position.
+// Frames are drawn directly into an indexed pixel buffer and encoded with the gif crate.
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::Duration;
+
+use gif::{Encoder, Frame, Repeat};
+
+use super::solver::Solver;
+use super::{CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+// palette indexes
+const BACKGROUND: u8 = 0;
+const PEG: u8 = 1;
+const JUMPING: u8 = 2;
+const JUMPED: u8 = 3;
+const LANDING: u8 = 4;
+
+// smallest cell size keeping a visible ring around the holes
+const MIN_CELL_SIZE: u16 = 8;
+
+const PALETTE: [u8; 15] = [
+    0xff, 0xff, 0xff,
+    0x44, 0x44, 0x44,
+    0x2a, 0x7a, 0xb8,
+    0xd9, 0x53, 0x4f,
+    0x5c, 0xb8, 0x5c,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationOptions {
+    pub cell_size: u16,
+    pub frame_delay: Duration,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> AnimationOptions {
+        AnimationOptions { cell_size: 40, frame_delay: Duration::from_millis(800) }
+    }
+}
+
+pub fn test_animation(rules: PyramideRules, dir: &Path) -> io::Result<()> {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let start = Pyramide::init_from_seed(rules.lines, full_seed & !1, rules.diag_allowed);
+    if let Some(solution) = Solver::new(&start, None).solve(&start) {
+        std::fs::create_dir_all(dir)?;
+        let file = BufWriter::new(File::create(dir.join("solution.gif"))?);
+        write_solution_gif(&start, &solution, AnimationOptions::default(), file)?;
+    }
+    Ok(())
+}
+
+fn image_size(lines: usize, cell_size: usize) -> (usize, usize) {
+    // half a cell of margin on every side
+    (cell_size * 2 * lines, cell_size * (lines + 1))
+}
+
+struct Canvas {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn disk(&mut self, cx: usize, cy: usize, radius: usize, inner_radius: usize, color: u8) {
+        // ring between inner_radius and radius, a full disk when inner_radius is 0
+        let (r2, inner2) = ((radius * radius) as isize, (inner_radius * inner_radius) as isize);
+        for y in cy - radius..=cy + radius {
+            for x in cx - radius..=cx + radius {
+                let (dx, dy) = (x as isize - cx as isize, y as isize - cy as isize);
+                let d2 = dx * dx + dy * dy;
+                if d2 <= r2 && (inner_radius == 0 || d2 >= inner2) {
+                    self.pixels[y * self.width + x] = color;
+                }
+            }
+        }
+    }
+}
+
+fn draw_board(pyra: &Pyramide, cell_size: usize, highlight: Option<[CellIndex; 3]>) -> Vec<u8> {
+    let (width, height) = image_size(pyra.lines, cell_size);
+    let mut canvas = Canvas { width, pixels: vec![BACKGROUND; width * height] };
+    let radius = cell_size * 3 / 8;
+    let stroke = (cell_size / 16).max(1);
+    for (idx, cell) in pyra.iter().enumerate() {
+        let (row, col) = pyra.cell_position(idx);
+        let (cx, cy) = (cell_size * (col + 1), cell_size * (row + 1));
+        match (highlight, cell) {
+            (Some([dep, _, _]), _) if dep == idx => canvas.disk(cx, cy, radius, 0, JUMPING),
+            (Some([_, mil, _]), _) if mil == idx => canvas.disk(cx, cy, radius, 0, JUMPED),
+            (Some([_, _, arr]), _) if arr == idx => canvas.disk(cx, cy, radius, radius - 2 * stroke, LANDING),
+            (_, Some(true)) => canvas.disk(cx, cy, radius, 0, PEG),
+            _ => canvas.disk(cx, cy, radius, radius - stroke, PEG),
+        }
+    }
+    canvas.pixels
+}
+
+pub fn solution_frames(start: &Pyramide, coups: &[Coup], cell_size: usize) -> Result<Vec<Vec<u8>>, ()> {
+    // indexed pixels of every frame, one per move plus the final position
+    let mut frames = vec![];
+    let mut pyra = start.clone();
+    for coup in coups {
+        if !pyra.is_coup_valid(coup) {
+            return Err(())
+        }
+        frames.push(draw_board(&pyra, cell_size, pyra.coup_cells(coup)));
+        pyra.coup(coup)?;
+    }
+    frames.push(draw_board(&pyra, cell_size, None));
+    return Ok(frames)
+}
+
+pub fn write_solution_gif<W: io::Write>(start: &Pyramide, coups: &[Coup], options: AnimationOptions, writer: W) -> io::Result<()> {
+    if options.cell_size < MIN_CELL_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "cell size too small"))
+    }
+    let cell_size = options.cell_size as usize;
+    let (width, height) = image_size(start.lines, cell_size);
+    let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "image too large for a gif")),
+    };
+    let frames = solution_frames(start, coups, cell_size)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid move in the solution"))?;
+    // gif delays are in hundredths of a second
+    let delay = u16::try_from(options.frame_delay.as_millis() / 10).unwrap_or(u16::MAX);
+
+    let mut encoder = Encoder::new(writer, width, height, &PALETTE).map_err(io::Error::other)?;
+    encoder.set_repeat(Repeat::Infinite).map_err(io::Error::other)?;
+    for pixels in frames {
+        let mut frame = Frame::from_indexed_pixels(width, height, pixels, None);
+        frame.delay = delay;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::{Direction, Orientation};
+
+    #[test]
+    fn test_solution_frames() {
+        let pyra = Pyramide::init_from_seed(3, 230, false);
+        let coups = [
+            Coup {mil: 2, orientation: Orientation::Horizontal, dir: Direction::Bas},
+            Coup {mil: 5, orientation: Orientation::Horizontal, dir: Direction::Haut},
+        ];
+        let frames = solution_frames(&pyra, &coups, 16).expect("valid moves");
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|frame| frame.len() == 96 * 64));
+        assert!(frames[0].contains(&JUMPED) && frames[0].contains(&LANDING));
+        assert!(!frames[2].contains(&JUMPING));
+        assert_eq!(solution_frames(&pyra, &[coups[0], coups[0]], 16), Err(()));
+    }
+
+    #[test]
+    fn test_write_solution_gif() {
+        let pyra = Pyramide::init_from_seed(3, 230, false);
+        let coups = [Coup {mil: 2, orientation: Orientation::Horizontal, dir: Direction::Bas}];
+        let options = AnimationOptions { cell_size: 20, frame_delay: Duration::from_millis(500) };
+        let mut data = vec![];
+        write_solution_gif(&pyra, &coups, options, &mut data).expect("written");
+
+        let mut decoder = gif::DecodeOptions::new().read_info(data.as_slice()).expect("valid gif");
+        assert_eq!((decoder.width(), decoder.height()), (120, 80));
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().expect("valid frame") {
+            assert_eq!(frame.delay, 50);
+            count += 1;
+        }
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,248 @@
+// Beam search, approximate answers for the boards beyond exact reach : the positions are
+// explored layer by layer (one peg less at each layer) and only the best width positions of
+// each layer, according to a heuristic, are expanded. The result is the position with the
+// fewest pegs found, with its moves.
+
+use std::collections::HashSet;
+
+use super::pagoda::{best_pagoda, Pagoda};
+use super::{cherche_coups_possibles, coups_theoriques, jump_partners, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_beam(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = Seed::MAX >> (Seed::BITS as usize - cells_num);
+    let start = Pyramide::init_from_seed(rules.lines, full_seed & !1, rules.diag_allowed);
+    let heuristics: Vec<Box<dyn Heuristic>> = vec![
+        Box::new(Mobility),
+        Box::new(IsolatedPegs::new(rules)),
+        Box::new(PegSpread),
+        Box::new(PagodaScore::new(rules, start.seed(), 1)),
+        Box::new(Combined(vec![(1.0, Box::new(Mobility)), (2.0, Box::new(IsolatedPegs::new(rules)))])),
+    ];
+    for heuristic in heuristics {
+        let name = heuristic.name();
+        let result = BeamSearch::new(rules, 1000, heuristic).search(&start);
+        println!("{} : {} pegs left, {} positions expanded", name, result.end.seed().count_ones(), result.expanded);
+    }
+}
+
+pub trait Heuristic {
+    // higher is better
+    fn score(&self, pyra: &Pyramide) -> f64;
+
+    fn name(&self) -> String;
+}
+
+// number of legal moves
+pub struct Mobility;
+
+impl Heuristic for Mobility {
+    fn score(&self, pyra: &Pyramide) -> f64 {
+        let coups = coups_theoriques(pyra);
+        cherche_coups_possibles(pyra, &coups).map_or(0, |coups| coups.len()) as f64
+    }
+
+    fn name(&self) -> String {
+        "mobility".to_string()
+    }
+}
+
+// minus the number of pegs without a neighbouring peg to jump with
+pub struct IsolatedPegs {
+    // neighbours[cell] : cells forming a (departure, middle) pair with cell
+    neighbours: Vec<Seed>,
+}
+
+impl IsolatedPegs {
+    pub fn new(rules: PyramideRules) -> IsolatedPegs {
+        IsolatedPegs { neighbours: jump_partners(rules) }
+    }
+
+    pub fn isolated(&self, seed: Seed) -> usize {
+        (0..self.neighbours.len())
+            .filter(|cell| seed & (1 << cell) != 0 && seed & self.neighbours[*cell] == 0)
+            .count()
+    }
+}
+
+impl Heuristic for IsolatedPegs {
+    fn score(&self, pyra: &Pyramide) -> f64 {
+        -(self.isolated(pyra.seed()) as f64)
+    }
+
+    fn name(&self) -> String {
+        "isolated pegs".to_string()
+    }
+}
+
+// value of a pagoda above the value of the target, the margin left to reach it
+pub struct PagodaScore {
+    pagoda: Pagoda,
+    target_value: i32,
+}
+
+impl PagodaScore {
+    pub fn new(rules: PyramideRules, start: Seed, target: Seed) -> PagodaScore {
+        let pagoda = best_pagoda(rules, start, target);
+        let target_value = pagoda.value(target);
+        PagodaScore { pagoda, target_value }
+    }
+}
+
+impl Heuristic for PagodaScore {
+    fn score(&self, pyra: &Pyramide) -> f64 {
+        (self.pagoda.value(pyra.seed()) - self.target_value) as f64
+    }
+
+    fn name(&self) -> String {
+        "pagoda".to_string()
+    }
+}
+
+// minus the mean squared distance of the pegs to their barycenter, grouped pegs first
+pub struct PegSpread;
+
+impl Heuristic for PegSpread {
+    fn score(&self, pyra: &Pyramide) -> f64 {
+        let seed = pyra.seed();
+        // a column step is half a cell wide, a row is about 0.87 cell high
+        let positions: Vec<(f64, f64)> = (0..pyra.lines * pyra.lines)
+            .filter(|cell| seed & (1 << cell) != 0)
+            .map(|cell| {
+                let (row, col) = pyra.cell_position(cell);
+                (row as f64 * 0.75_f64.sqrt(), col as f64 * 0.5)
+            })
+            .collect();
+        if positions.is_empty() {
+            return 0.0
+        }
+        let n = positions.len() as f64;
+        let (mean_y, mean_x) = positions.iter().fold((0.0, 0.0), |(y, x), (py, px)| (y + py / n, x + px / n));
+        -positions.iter().map(|(y, x)| (y - mean_y).powi(2) + (x - mean_x).powi(2)).sum::<f64>() / n
+    }
+
+    fn name(&self) -> String {
+        "peg spread".to_string()
+    }
+}
+
+// weighted sum of heuristics
+pub struct Combined(pub Vec<(f64, Box<dyn Heuristic>)>);
+
+impl Heuristic for Combined {
+    fn score(&self, pyra: &Pyramide) -> f64 {
+        self.0.iter().map(|(weight, heuristic)| weight * heuristic.score(pyra)).sum()
+    }
+
+    fn name(&self) -> String {
+        let names: Vec<String> = self.0.iter().map(|(weight, heuristic)| format!("{} {}", weight, heuristic.name())).collect();
+        names.join(" + ")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeamResult {
+    // position with the fewest pegs found
+    pub end: Pyramide,
+    pub coups: Vec<Coup>,
+    pub expanded: usize,
+}
+
+pub struct BeamSearch {
+    coups: Vec<Coup>,
+    width: usize,
+    heuristic: Box<dyn Heuristic>,
+}
+
+impl BeamSearch {
+    pub fn new(rules: PyramideRules, width: usize, heuristic: Box<dyn Heuristic>) -> BeamSearch {
+        assert!(width > 0);
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        BeamSearch { coups, width, heuristic }
+    }
+
+    pub fn search(&self, start: &Pyramide) -> BeamResult {
+        // the moves are kept as (parent, move) links, layer by layer
+        let mut links: Vec<Vec<(usize, Coup)>> = vec![];
+        let mut layer: Vec<Pyramide> = vec![start.clone()];
+        let mut expanded = 0;
+        loop {
+            let mut seen: HashSet<Seed> = HashSet::new();
+            let mut next: Vec<(f64, Pyramide, usize, Coup)> = vec![];
+            for (parent, pyra) in layer.iter().enumerate() {
+                expanded += 1;
+                for coup in cherche_coups_possibles(pyra, &self.coups).unwrap_or_default() {
+                    let mut new_pyra = pyra.clone();
+                    new_pyra.coup(&coup).expect("already checked");
+                    if seen.insert(new_pyra.seed()) {
+                        next.push((self.heuristic.score(&new_pyra), new_pyra, parent, coup));
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            // ties broken by seed, the result does not depend on the hash order
+            next.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.seed().cmp(&b.1.seed())));
+            next.truncate(self.width);
+            links.push(next.iter().map(|(_, _, parent, coup)| (*parent, *coup)).collect());
+            layer = next.into_iter().map(|(_, pyra, _, _)| pyra).collect();
+        }
+
+        // the best position of the last layer, walked back to the start
+        let mut coups = vec![];
+        let mut idx = 0;
+        for layer_links in links.iter().rev() {
+            let (parent, coup) = layer_links[idx];
+            coups.push(coup);
+            idx = parent;
+        }
+        coups.reverse();
+        BeamResult { end: layer.swap_remove(0), coups, expanded }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::pagoda::find_pagoda;
+
+    #[test]
+    fn test_heuristics() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        // a4 above b4, d7 alone
+        let pyra = Pyramide::init_from_seed(4, 0b1000_0000_0000_0101, false);
+        assert_eq!(IsolatedPegs::new(rules).isolated(pyra.seed()), 1);
+        assert_eq!(IsolatedPegs::new(rules).score(&Pyramide::init_full(4, false)), 0.0);
+        assert_eq!(Mobility.score(&pyra), 1.0);
+        assert_eq!(Mobility.score(&Pyramide::init_from_seed(4, 0xFFFF & !1, true)), 3.0);
+        assert!(PegSpread.score(&pyra) < PegSpread.score(&Pyramide::init_from_seed(4, 0b111, false)));
+        let pagoda = PagodaScore::new(rules, 0xFFFF & !1, 1);
+        let negative = pagoda.score(&Pyramide::init_from_seed(4, 0xFFFF & !1, false)) < 0.0;
+        assert_eq!(negative, find_pagoda(rules, 0xFFFF & !1, 1).is_some());
+        let combined = Combined(vec![(1.0, Box::new(Mobility)), (0.5, Box::new(IsolatedPegs::new(rules)))]);
+        assert_eq!(combined.score(&pyra), 0.5);
+        assert_eq!(combined.name(), "1 mobility + 0.5 isolated pegs");
+    }
+
+    #[test]
+    fn test_search() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        // wide enough to keep every position
+        let result = BeamSearch::new(rules, 10_000, Box::new(Mobility)).search(&start);
+        assert!(result.end.partie_gagne());
+        assert_eq!(result.coups.len(), 14);
+
+        // greedy, a legal line leading to the end position
+        let result = BeamSearch::new(rules, 1, Box::new(PegSpread)).search(&start);
+        assert_eq!(result.expanded, result.coups.len() + 1);
+        let mut pyra = start.clone();
+        for coup in &result.coups {
+            pyra.coup(coup).expect("legal move");
+        }
+        assert_eq!(pyra, result.end);
+        assert_eq!(cherche_coups_possibles(&pyra, &coups_theoriques(&pyra)), None);
+    }
+}
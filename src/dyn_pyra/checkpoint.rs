@@ -0,0 +1,249 @@
+// Periodic on disk snapshots of long running computations, so that they can resume
+// where they stopped after an interruption.
+// A checkpoint file is tied to the PyramideRules it was computed with, a checkpoint
+// written for other rules is rejected on load.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::{PyramideRules, Seed};
+
+const MAGIC: &[u8; 4] = b"PYRA";
+const VERSION: u8 = 1;
+
+pub struct Checkpoint {
+    dir: PathBuf,
+    rules: PyramideRules,
+    interval: Duration,
+    last_save: Instant,
+}
+
+impl Checkpoint {
+    pub fn new(dir: impl Into<PathBuf>, rules: PyramideRules, interval: Duration) -> Checkpoint {
+        Checkpoint { dir: dir.into(), rules, interval, last_save: Instant::now() }
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.rules
+    }
+
+    fn path(&self, kind: &str) -> PathBuf {
+        let diag = if self.rules.diag_allowed { "diag" } else { "nodiag" };
+        self.dir.join(format!("{}_{}_{}.ckpt", kind, self.rules.lines, diag))
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.last_save.elapsed() >= self.interval
+    }
+
+    pub fn save(&mut self, kind: &str, data: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut enc = Encoder::new();
+        enc.bytes(MAGIC);
+        enc.u8(VERSION);
+        enc.u64(self.rules.lines as u64);
+        enc.u8(self.rules.diag_allowed as u8);
+        enc.bytes(data);
+        // write then rename, an interruption during the write keeps the previous checkpoint
+        let path = self.path(kind);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, enc.finish())?;
+        fs::rename(&tmp_path, &path)?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    pub fn load(&self, kind: &str) -> io::Result<Option<Vec<u8>>> {
+        let data = match fs::read(self.path(kind)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut dec = Decoder::new(&data);
+        if dec.bytes(MAGIC.len())? != MAGIC || dec.u8()? != VERSION {
+            return Err(invalid_data("not a checkpoint file"))
+        }
+        let rules = PyramideRules { lines: dec.u64()? as usize, diag_allowed: dec.u8()? != 0 };
+        if rules != self.rules {
+            return Err(invalid_data("checkpoint was written for other rules"))
+        }
+        Ok(Some(dec.rest().to_vec()))
+    }
+
+    pub fn clear(&self, kind: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(kind)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+// Little endian binary encoding of the checkpoint payloads
+
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder::default()
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub fn u16(&mut self, val: u16) {
+        self.bytes(&val.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, val: u32) {
+        self.bytes(&val.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, val: u64) {
+        self.bytes(&val.to_le_bytes());
+    }
+
+    pub fn seed(&mut self, val: Seed) {
+        self.bytes(&val.to_le_bytes());
+    }
+
+    pub fn u16s(&mut self, vals: &[u16]) {
+        self.u64(vals.len() as u64);
+        vals.iter().for_each(|v| self.u16(*v));
+    }
+
+    pub fn u32s(&mut self, vals: &[u32]) {
+        self.u64(vals.len() as u64);
+        vals.iter().for_each(|v| self.u32(*v));
+    }
+
+    pub fn seeds(&mut self, vals: &[Seed]) {
+        self.u64(vals.len() as u64);
+        vals.iter().for_each(|v| self.seed(*v));
+    }
+}
+
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Decoder<'a> {
+        Decoder { buf }
+    }
+
+    pub fn rest(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    pub fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() < len {
+            return Err(invalid_data("truncated checkpoint"))
+        }
+        let (res, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(res)
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().expect("2 bytes")))
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().expect("4 bytes")))
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().expect("8 bytes")))
+    }
+
+    pub fn seed(&mut self) -> io::Result<Seed> {
+        Ok(Seed::from_le_bytes(self.bytes(16)?.try_into().expect("16 bytes")))
+    }
+
+    fn len(&mut self, elem_size: usize) -> io::Result<usize> {
+        let len = self.u64()? as usize;
+        if len.saturating_mul(elem_size) > self.buf.len() {
+            return Err(invalid_data("truncated checkpoint"))
+        }
+        Ok(len)
+    }
+
+    pub fn u16s(&mut self) -> io::Result<Vec<u16>> {
+        let len = self.len(2)?;
+        (0..len).map(|_| self.u16()).collect()
+    }
+
+    pub fn u32s(&mut self) -> io::Result<Vec<u32>> {
+        let len = self.len(4)?;
+        (0..len).map(|_| self.u32()).collect()
+    }
+
+    pub fn seeds(&mut self) -> io::Result<Vec<Seed>> {
+        let len = self.len(16)?;
+        (0..len).map(|_| self.seed()).collect()
+    }
+}
+
+
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pyramide_{}_{}", name, std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_clear() {
+        let dir = test_dir("checkpoint");
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let mut checkpoint = Checkpoint::new(&dir, rules, Duration::ZERO);
+        assert!(checkpoint.is_due());
+        assert_eq!(checkpoint.load("test").unwrap(), None);
+
+        let mut enc = Encoder::new();
+        enc.u32s(&[1, 2, 3]);
+        enc.seeds(&[1 << 100]);
+        checkpoint.save("test", &enc.finish()).unwrap();
+        let data = checkpoint.load("test").unwrap().expect("saved");
+        let mut dec = Decoder::new(&data);
+        assert_eq!(dec.u32s().unwrap(), vec![1, 2, 3]);
+        assert_eq!(dec.seeds().unwrap(), vec![1 << 100]);
+        assert!(dec.u8().is_err());
+
+        let other = Checkpoint::new(&dir, PyramideRules { lines: 4, diag_allowed: false }, Duration::ZERO);
+        assert_eq!(other.load("test").unwrap(), None);
+        std::fs::copy(checkpoint.path("test"), other.path("test")).unwrap();
+        assert!(other.load("test").is_err());
+
+        checkpoint.clear("test").unwrap();
+        assert_eq!(checkpoint.load("test").unwrap(), None);
+        checkpoint.clear("test").unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+// Position classes : a jump from a over b to c flips the three cells a, b and c. Any set of
+// cells meeting every jump line in an even number of cells keeps the parity of its number of
+// pegs, these sets are the solutions of a linear system over GF(2).
+// Two positions with a different class (parities for a basis of such sets) are never connected.
+
+use std::fmt::Write;
+
+use super::{coups_theoriques, CellIndex, Pyramide, PyramideRules, Seed};
+
+pub type PositionClass = u128;
+
+pub fn test_classes(rules: PyramideRules) {
+    let classes = PositionClasses::new(rules);
+    println!("Number of invariant cell sets : {:?}", classes.masks.len());
+    print!("{}", impossible_finishes_report(rules));
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionClasses {
+    rules: PyramideRules,
+    // basis of the invariant cell sets
+    masks: Vec<Seed>,
+}
+
+impl PositionClasses {
+    pub fn new(rules: PyramideRules) -> PositionClasses {
+        let cells_num = rules.lines * rules.lines;
+        let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+        let jump_lines: Vec<Seed> = coups_theoriques(&pyra).iter()
+            .filter_map(|coup| pyra.coup_cells(coup))
+            .map(|cells| cells.iter().map(|cell| 1 << cell).sum())
+            .collect();
+
+        // reduced row echelon form of the jump lines
+        let mut rows: Vec<Seed> = vec![];
+        let mut pivots: Vec<CellIndex> = vec![];
+        for mut line in jump_lines {
+            for (row, pivot) in rows.iter().zip(&pivots) {
+                if line & (1 << pivot) != 0 {
+                    line ^= row;
+                }
+            }
+            if line == 0 {
+                continue;
+            }
+            let pivot = line.trailing_zeros() as CellIndex;
+            for row in rows.iter_mut() {
+                if *row & (1 << pivot) != 0 {
+                    *row ^= line;
+                }
+            }
+            rows.push(line);
+            pivots.push(pivot);
+        }
+
+        // one invariant set per free cell
+        let masks = (0..cells_num)
+            .filter(|cell| !pivots.contains(cell))
+            .map(|free| {
+                let pivot_cells: Seed = rows.iter().zip(&pivots)
+                    .filter(|(row, _)| *row & (1 << free) != 0)
+                    .map(|(_, pivot)| 1 << pivot)
+                    .sum();
+                pivot_cells | (1 << free)
+            })
+            .collect();
+        PositionClasses { rules, masks }
+    }
+
+    pub fn class(&self, seed: Seed) -> PositionClass {
+        self.masks.iter().enumerate()
+            .map(|(i, mask)| (((seed & mask).count_ones() % 2) as PositionClass) << i)
+            .sum()
+    }
+
+    pub fn may_reach(&self, from: Seed, to: Seed) -> bool {
+        self.class(from) == self.class(to)
+    }
+}
+
+pub fn impossible_finishes(rules: PyramideRules) -> Vec<(CellIndex, CellIndex)> {
+    // (starting hole, final cell) pairs ruled out by the position classes
+    let classes = PositionClasses::new(rules);
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let mut res = vec![];
+    for hole in 0..cells_num {
+        for cell in 0..cells_num {
+            if !classes.may_reach(full_seed & !(1 << hole), 1 << cell) {
+                res.push((hole, cell));
+            }
+        }
+    }
+    return res
+}
+
+pub fn impossible_finishes_report(rules: PyramideRules) -> String {
+    // one line per starting hole, x for a final cell ruled out by the position classes
+    let cells_num = rules.lines * rules.lines;
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    let impossible = impossible_finishes(rules);
+    let mut report = "hole".to_string();
+    for cell in 0..cells_num {
+        write!(report, " {:>3}", pyra.cell_name(cell)).unwrap();
+    }
+    report.push('\n');
+    for hole in 0..cells_num {
+        write!(report, "{:>4}", pyra.cell_name(hole)).unwrap();
+        for cell in 0..cells_num {
+            let mark = if impossible.contains(&(hole, cell)) { "x" } else { "." };
+            write!(report, " {:>3}", mark).unwrap();
+        }
+        report.push('\n');
+    }
+    return report
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::state_graph::StateGraph;
+
+    #[test]
+    fn test_masks_are_invariant() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let classes = PositionClasses::new(rules);
+            let graph = StateGraph::build_full(rules);
+            for idx in 0..graph.node_count() as u32 {
+                for target in graph.neighbors_slice(idx) {
+                    assert_eq!(classes.class(graph.seed(idx)), classes.class(graph.seed(*target)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_masks_without_diagonals() {
+        // a jump line holds one cell of each (row + column) mod 3 color, the union of two colors
+        // is invariant
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let classes = PositionClasses::new(rules);
+        let pyra = Pyramide::new(4, false);
+        let two_colors: Seed = (0..16)
+            .filter(|cell| {
+                let (row, col) = pyra.cell_position(*cell);
+                (row + col) % 3 != 0
+            })
+            .map(|cell| 1 << cell)
+            .sum();
+        let k = classes.masks.len();
+        let in_span = (0..1_u32 << k).any(|comb| {
+            let combination = (0..k)
+                .filter(|i| comb & (1 << i) != 0)
+                .fold(0, |acc, i| acc ^ classes.masks[i]);
+            combination == two_colors
+        });
+        assert!(in_span);
+    }
+
+    #[test]
+    fn test_impossible_finishes() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let impossible = impossible_finishes(rules);
+        assert!(!impossible.is_empty());
+        let classes = PositionClasses::new(rules);
+        for (hole, cell) in impossible {
+            assert_ne!(classes.class(0xFFFF & !(1 << hole)), classes.class(1 << cell));
+        }
+        let report = impossible_finishes_report(rules);
+        assert_eq!(report.lines().count(), 17);
+    }
+}
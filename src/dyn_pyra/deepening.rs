@@ -0,0 +1,264 @@
+// Iterative deepening depth first search with a transposition table, the memory bounded
+// alternative to the breadth first naive_pyra::brute_force_search.
+// Every move removes a peg, a line of d moves ends with pegs - d pegs : the search looks for a
+// line of 1, 2, ... moves, the line of pegs - 1 moves being a win. A position without a line of
+// d moves has none of d' > d moves either, the table remembers the smallest such d by canonical
+// seed (a position and its mirror image have the same lines).
+// The table has a fixed number of buckets of two entries : the first one keeps the entry that
+// cost the most nodes to prove, the second one is always replaced.
+
+use std::cmp::Reverse;
+
+use super::{coups_theoriques, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_deepening(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = Seed::MAX >> (Seed::BITS as usize - cells_num);
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    for order in [MoveOrder::Natural, MoveOrder::Center, MoveOrder::Bottom] {
+        let mut solver = DeepeningSolver::new(rules, order, 1 << 20);
+        for hole in 0..cells_num {
+            let start = Pyramide::init_from_seed(rules.lines, full_seed & !(1 << hole), rules.diag_allowed);
+            let line = solver.deepen(&start);
+            println!("{:?} starting hole {} : {} pegs left", order, pyra.cell_name(hole), cells_num - 1 - line.len());
+        }
+        println!("{:?} : {} nodes explored, {} table hits, {} entries replaced",
+            order, solver.explored, solver.table.hits, solver.table.replaced);
+    }
+}
+
+pub fn mirror_seed(lines: usize, seed: Seed) -> Seed {
+    // left-right mirror image, the cell k of row r goes to 2r - k
+    let mut mirror = 0;
+    for row in 0..lines {
+        let first = row * row;
+        for k in 0..=2 * row {
+            if seed & (1 << (first + k)) != 0 {
+                mirror |= 1 << (first + 2 * row - k);
+            }
+        }
+    }
+    mirror
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveOrder {
+    // order of coups_theoriques
+    Natural,
+    // arrival cell closest to the center of the board first
+    Center,
+    // arrival cell on the lowest row first
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    seed: Seed,
+    // no line of depth moves from seed, 0 for an empty entry
+    depth: u8,
+    // nodes explored to prove it
+    work: u32,
+}
+
+pub struct TranspositionTable {
+    buckets: Vec<[Entry; 2]>,
+    pub hits: usize,
+    pub replaced: usize,
+}
+
+impl TranspositionTable {
+    pub fn new(entries: usize) -> TranspositionTable {
+        TranspositionTable { buckets: vec![[Entry::default(); 2]; entries.div_ceil(2).max(1)], hits: 0, replaced: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * 2
+    }
+
+    fn bucket(&self, seed: Seed) -> usize {
+        let hash = (seed as u64 ^ (seed >> 64) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        (hash >> 32) as usize % self.buckets.len()
+    }
+
+    fn fails(&mut self, seed: Seed, depth: u8) -> bool {
+        // true when seed is known to have no line of depth moves
+        let bucket = self.bucket(seed);
+        let found = self.buckets[bucket].iter().any(|entry| entry.depth != 0 && entry.seed == seed && entry.depth <= depth);
+        self.hits += found as usize;
+        found
+    }
+
+    fn store(&mut self, seed: Seed, depth: u8, work: u32) {
+        let new = Entry { seed, depth, work };
+        let idx = self.bucket(seed);
+        let bucket = &mut self.buckets[idx];
+        if let Some(entry) = bucket.iter_mut().find(|entry| entry.depth != 0 && entry.seed == seed) {
+            if depth <= entry.depth {
+                *entry = new;
+            }
+            return
+        }
+        let [first, second] = *bucket;
+        if first.depth == 0 || work >= first.work {
+            // the entry that cost the most is kept, in the always replaced slot
+            *bucket = [new, first];
+            self.replaced += (first.depth != 0 && second.depth != 0) as usize;
+        } else {
+            bucket[1] = new;
+            self.replaced += (second.depth != 0) as usize;
+        }
+    }
+}
+
+pub struct DeepeningSolver {
+    rules: PyramideRules,
+    coups: Vec<Coup>,
+    pub table: TranspositionTable,
+    pub explored: usize,
+}
+
+impl DeepeningSolver {
+    pub fn new(rules: PyramideRules, order: MoveOrder, table_entries: usize) -> DeepeningSolver {
+        let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+        let mut coups: Vec<Coup> = coups_theoriques(&pyra).into_iter()
+            .filter(|coup| pyra.coup_cells(coup).is_some())
+            .collect();
+        // rows and columns counted in thirds of a row, the centroid of the board is at
+        // row 2 * (lines - 1) / 3, its column at the middle of the rows
+        let lines = rules.lines as i64;
+        let arrival = |coup: &Coup| pyra.cell_position(pyra.coup_cells(coup).expect("on the board")[2]);
+        match order {
+            MoveOrder::Natural => {},
+            MoveOrder::Center => coups.sort_by_key(|coup| {
+                let (row, col) = arrival(coup);
+                (3 * row as i64 - 2 * (lines - 1)).pow(2) + 3 * (col as i64 - (lines - 1)).pow(2)
+            }),
+            MoveOrder::Bottom => coups.sort_by_key(|coup| Reverse(arrival(coup).0)),
+        }
+        DeepeningSolver { rules, coups, table: TranspositionTable::new(table_entries), explored: 0 }
+    }
+
+    fn canonical(&self, seed: Seed) -> Seed {
+        seed.min(mirror_seed(self.rules.lines, seed))
+    }
+
+    fn search(&mut self, pyra: &Pyramide, depth: u8, line: &mut Vec<Coup>) -> bool {
+        // line of depth moves from pyra, appended to line
+        if depth == 0 {
+            return true
+        }
+        let seed = self.canonical(pyra.seed());
+        if self.table.fails(seed, depth) {
+            return false
+        }
+        self.explored += 1;
+        let explored = self.explored;
+        for idx in 0..self.coups.len() {
+            let coup = self.coups[idx];
+            if !pyra.is_coup_valid(&coup) {
+                continue;
+            }
+            let mut new_pyra = pyra.clone();
+            new_pyra.coup(&coup).expect("already checked");
+            line.push(coup);
+            if self.search(&new_pyra, depth - 1, line) {
+                return true
+            }
+            line.pop();
+        }
+        let work = (self.explored - explored + 1).min(u32::MAX as usize) as u32;
+        self.table.store(seed, depth, work);
+        return false
+    }
+
+    pub fn solve(&mut self, pyra: &Pyramide) -> Option<Vec<Coup>> {
+        // winning line, the longest line when it leaves a single peg
+        let pegs = pyra.seed().count_ones() as usize;
+        let line = self.deepen(pyra);
+        if pegs > 0 && line.len() == pegs - 1 {
+            return Some(line)
+        }
+        return None
+    }
+
+    pub fn deepen(&mut self, pyra: &Pyramide) -> Vec<Coup> {
+        // longest line, found with searches of increasing depth
+        let pegs = pyra.seed().count_ones() as u8;
+        let mut best = vec![];
+        for depth in 1..pegs {
+            let mut line = vec![];
+            if !self.search(pyra, depth, &mut line) {
+                break;
+            }
+            best = line;
+        }
+        return best
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::retrograde::winnable_seeds;
+
+    #[test]
+    fn test_mirror_seed() {
+        // a3 alone, b2 and b4 mirrored
+        assert_eq!(mirror_seed(3, 0b1), 0b1);
+        assert_eq!(mirror_seed(3, 0b0010), 0b1000);
+        for seed in [0xFFFE, 0x1234, 0xBEEF] {
+            assert_eq!(mirror_seed(4, mirror_seed(4, seed)), seed);
+            assert_eq!(mirror_seed(4, seed).count_ones(), seed.count_ones());
+        }
+        let winnable = winnable_seeds(PyramideRules { lines: 4, diag_allowed: false });
+        for seed in 1..(1 << 16) {
+            assert_eq!(winnable.contains(seed), winnable.contains(mirror_seed(4, seed as Seed) as usize));
+        }
+    }
+
+    #[test]
+    fn test_solve_matches_retrograde() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let winnable = winnable_seeds(rules);
+            for order in [MoveOrder::Natural, MoveOrder::Center, MoveOrder::Bottom] {
+                // a small table, entries get replaced
+                let mut solver = DeepeningSolver::new(rules, order, 64);
+                for hole in 0..16 {
+                    let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), diag_allowed);
+                    let solution = solver.solve(&start);
+                    assert_eq!(solution.is_some(), winnable.contains(start.seed() as usize));
+                    if let Some(solution) = solution {
+                        let mut pyra = start.clone();
+                        for coup in solution {
+                            pyra.coup(&coup).expect("valid solution");
+                        }
+                        assert!(pyra.partie_gagne());
+                    }
+                }
+                assert!(solver.table.replaced > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deepen() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let mut solver = DeepeningSolver::new(rules, MoveOrder::Center, 1 << 12);
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, false);
+        let line = solver.deepen(&start);
+        let mut pyra = start.clone();
+        for coup in &line {
+            pyra.coup(coup).expect("valid line");
+        }
+        // the longest line ends without a move, a win is impossible from the a4 hole
+        assert!(!pyra.partie_gagne());
+        assert!(coups_theoriques(&pyra).iter().all(|coup| !pyra.is_coup_valid(coup)));
+        assert!(solver.table.hits > 0);
+
+        let mut solver = DeepeningSolver::new(PyramideRules { lines: 4, diag_allowed: true }, MoveOrder::Bottom, 1 << 12);
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        assert_eq!(solver.deepen(&start).len(), 14);
+    }
+}
@@ -0,0 +1,249 @@
+// Text diagrams of a board, in the layout of naive_pyra::Pyramide::print :
+//   1 2 3 4 5 6 7
+// a       1
+// b     1 1 1
+// c   1 1 0 1 1
+// d 1 1 1 1 1 1 1
+// Pyramide implements Display with the plain digits, Pyramide::display gives the options
+// (Unicode glyphs, ANSI colors, last move highlighting). Pyramide::from_diagram reads any of
+// these diagrams back.
+
+use std::fmt;
+
+use super::{CellIndex, Coup, Pyramide, Seed};
+
+// (peg, hole, landing peg of the last move, cell emptied by the last move)
+const ASCII_GLYPHS: [char; 4] = ['1', '0', '*', '.'];
+const UNICODE_GLYPHS: [char; 4] = ['●', '○', '◉', '◌'];
+
+const LANDING_COLOR: &str = "\x1b[1;32m";
+const EMPTIED_COLOR: &str = "\x1b[31m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+// a seed holds at most 128 cells
+const MAX_LINES: usize = 11;
+
+pub struct PyramideDisplay<'a> {
+    pyra: &'a Pyramide,
+    unicode: bool,
+    colors: bool,
+    last_move: Option<Coup>,
+}
+
+impl<'a> PyramideDisplay<'a> {
+    pub fn unicode(mut self, unicode: bool) -> PyramideDisplay<'a> {
+        self.unicode = unicode;
+        self
+    }
+
+    pub fn colors(mut self, colors: bool) -> PyramideDisplay<'a> {
+        self.colors = colors;
+        self
+    }
+
+    pub fn last_move(mut self, coup: Coup) -> PyramideDisplay<'a> {
+        // the move that led to this board, its cells are highlighted
+        self.last_move = Some(coup);
+        self
+    }
+}
+
+impl fmt::Display for PyramideDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pyra = self.pyra;
+        let columns = pyra.lines * 2 - 1;
+        let width = columns.to_string().len();
+        let glyphs = if self.unicode { UNICODE_GLYPHS } else { ASCII_GLYPHS };
+        let highlight: Option<[CellIndex; 3]> = self.last_move.and_then(|coup| pyra.coup_cells(&coup));
+
+        write!(f, " ")?;
+        for col in 1..=columns {
+            write!(f, " {:>width$}", col)?;
+        }
+        writeln!(f)?;
+
+        let mut cells = pyra.iter().enumerate().peekable();
+        for row in 0..pyra.lines {
+            write!(f, "{}", (b'a' + row as u8) as char)?;
+            // only the columns up to the last cell of the row, no trailing spaces
+            write!(f, "{}", " ".repeat((width + 1) * (pyra.lines - 1 - row)))?;
+            while let Some((idx, cell)) = cells.next_if(|(idx, _)| idx.isqrt() == row) {
+                let (glyph, color) = match (highlight, cell) {
+                    (Some([dep, mil, _]), _) if dep == idx || mil == idx => (glyphs[3], EMPTIED_COLOR),
+                    (Some([_, _, arr]), _) if arr == idx => (glyphs[2], LANDING_COLOR),
+                    (_, Some(true)) => (glyphs[0], ""),
+                    _ => (glyphs[1], ""),
+                };
+                write!(f, " {}", " ".repeat(width - 1))?;
+                if self.colors && !color.is_empty() {
+                    write!(f, "{}{}{}", color, glyph, RESET_COLOR)?;
+                } else {
+                    write!(f, "{}", glyph)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pyramide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display().fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    MissingHeader,
+    TooManyLines(usize),
+    MissingRow(char),
+    BadRow(char),
+    UnknownGlyph(char),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader => write!(f, "missing column numbers line"),
+            ParseError::TooManyLines(lines) => write!(f, "{} lines, at most {} are supported", lines, MAX_LINES),
+            ParseError::MissingRow(row) => write!(f, "missing row {}", row),
+            ParseError::BadRow(row) => write!(f, "wrong number of cells on row {}", row),
+            ParseError::UnknownGlyph(glyph) => write!(f, "unknown cell {:?}", glyph),
+        }
+    }
+}
+
+fn strip_colors(text: &str) -> String {
+    // removes the ANSI escape sequences ("\x1b[...m")
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+fn parse_glyph(glyph: &str) -> Result<bool, ParseError> {
+    let mut chars = glyph.chars();
+    let c = chars.next().expect("split_whitespace gives non empty words");
+    if chars.next().is_some() {
+        return Err(ParseError::UnknownGlyph(c))
+    }
+    if ASCII_GLYPHS[0] == c || ASCII_GLYPHS[2] == c || UNICODE_GLYPHS[0] == c || UNICODE_GLYPHS[2] == c {
+        return Ok(true)
+    }
+    if ASCII_GLYPHS[1] == c || ASCII_GLYPHS[3] == c || UNICODE_GLYPHS[1] == c || UNICODE_GLYPHS[3] == c {
+        return Ok(false)
+    }
+    Err(ParseError::UnknownGlyph(c))
+}
+
+impl Pyramide {
+    pub fn display(&self) -> PyramideDisplay<'_> {
+        PyramideDisplay { pyra: self, unicode: false, colors: false, last_move: None }
+    }
+
+    pub fn from_diagram(text: &str, diag_allowed: bool) -> Result<Pyramide, ParseError> {
+        let text = strip_colors(text);
+        let mut rows = text.lines().filter(|line| !line.trim().is_empty());
+
+        // the number of columns gives the number of lines
+        let header = rows.next().ok_or(ParseError::MissingHeader)?;
+        let columns = header.split_whitespace()
+            .enumerate()
+            .all(|(i, col)| col.parse() == Ok(i + 1))
+            .then(|| header.split_whitespace().count())
+            .filter(|columns| columns % 2 == 1)
+            .ok_or(ParseError::MissingHeader)?;
+        let lines = columns.div_ceil(2);
+        if lines > MAX_LINES {
+            return Err(ParseError::TooManyLines(lines))
+        }
+
+        let mut seed: Seed = 0;
+        for row in 0..lines {
+            let name = (b'a' + row as u8) as char;
+            let mut words = rows.next().ok_or(ParseError::MissingRow(name))?.split_whitespace();
+            if words.next() != Some(name.to_string().as_str()) {
+                return Err(ParseError::MissingRow(name))
+            }
+            let cells = words.map(parse_glyph).collect::<Result<Vec<bool>, ParseError>>()?;
+            if cells.len() != 2 * row + 1 {
+                return Err(ParseError::BadRow(name))
+            }
+            for (i, peg) in cells.into_iter().enumerate() {
+                if peg {
+                    seed |= 1 << (row * row + i);
+                }
+            }
+        }
+        Ok(Pyramide::init_from_seed(lines, seed, diag_allowed))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::{Direction, Orientation};
+
+    #[test]
+    fn test_display() {
+        let pyra = Pyramide::init_from_seed(4, 0xFFFF & !(1 << 6), false);
+        let expected = "  1 2 3 4 5 6 7\n\
+                        a       1\n\
+                        b     1 1 1\n\
+                        c   1 1 0 1 1\n\
+                        d 1 1 1 1 1 1 1\n";
+        assert_eq!(pyra.to_string(), expected);
+
+        // two digits column numbers
+        let text = Pyramide::init_full(6, false).to_string();
+        assert!(text.starts_with("   1  2  3"));
+        assert!(text.contains(&format!("\na{}1\n", " ".repeat(17))));
+        assert!(text.ends_with("f  1  1  1  1  1  1  1  1  1  1  1\n"));
+    }
+
+    #[test]
+    fn test_display_last_move() {
+        // c4 over b4 to a4 on the board above
+        let mut pyra = Pyramide::init_from_seed(4, 0xFFFF & !1, false);
+        let coup = Coup {mil: 2, orientation: Orientation::Vertical, dir: Direction::Haut};
+        pyra.coup(&coup).expect("valid move");
+        let text = pyra.display().last_move(coup).to_string();
+        assert!(text.contains("a       *\n"));
+        assert!(text.contains("b     1 . 1\n"));
+        assert!(text.contains("c   1 1 . 1 1\n"));
+
+        let text = pyra.display().unicode(true).colors(true).last_move(coup).to_string();
+        assert!(text.contains(&format!("a       {}◉{}\n", LANDING_COLOR, RESET_COLOR)));
+        assert!(text.contains("d ● ● ● ● ● ● ●\n"));
+    }
+
+    #[test]
+    fn test_from_diagram() {
+        let mut pyra = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let coup = Coup {mil: 2, orientation: Orientation::Vertical, dir: Direction::Haut};
+        pyra.coup(&coup).expect("valid move");
+        for unicode in [false, true] {
+            for colors in [false, true] {
+                let text = pyra.display().unicode(unicode).colors(colors).last_move(coup).to_string();
+                assert_eq!(Pyramide::from_diagram(&text, true), Ok(pyra.clone()));
+            }
+        }
+        for lines in 1..=MAX_LINES {
+            let pyra = Pyramide::init_from_seed(lines, 0x5555_5555_5555_5555_5555_5555_5555_5555 >> (128 - lines * lines), false);
+            assert_eq!(Pyramide::from_diagram(&pyra.to_string(), false), Ok(pyra));
+        }
+
+        assert_eq!(Pyramide::from_diagram("", false), Err(ParseError::MissingHeader));
+        assert_eq!(Pyramide::from_diagram("  1 2 3\na   1\n", false), Err(ParseError::MissingRow('b')));
+        assert_eq!(Pyramide::from_diagram("  1 2 3\na   1\nb 1 1\n", false), Err(ParseError::BadRow('b')));
+        assert_eq!(Pyramide::from_diagram("  1 2 3\na   1\nb 1 x 1\n", false), Err(ParseError::UnknownGlyph('x')));
+    }
+}
@@ -0,0 +1,243 @@
+// Breadth first exploration of the positions reachable from a start, with the frontiers on disk.
+// Every move removes a peg, the layer of pegs - 1 pegs is exactly the set of successors of the
+// layer of pegs pegs : each layer is streamed from its file, its successors are sorted and
+// deduplicated by chunks of bounded size written as runs, then the runs are merged into the
+// next layer file. Only a chunk of seeds is kept in memory.
+// Layer files hold sorted seeds of (lines*lines + 7) / 8 bytes, little endian. A layer file is
+// renamed in place once complete, an interrupted exploration resumes from the last one.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::{cherche_coups_possibles, coups_theoriques, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_external_bfs(rules: PyramideRules, dir: &Path) -> io::Result<()> {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = Seed::MAX >> (Seed::BITS as usize - cells_num);
+    let start = Pyramide::init_from_seed(rules.lines, full_seed & !1, rules.diag_allowed);
+    let bfs = ExternalBfs::new(dir, rules, 1 << 24);
+    for (pegs, size) in bfs.run(&start)? {
+        println!("{:>3} pegs : {} positions", pegs, size);
+    }
+    Ok(())
+}
+
+pub struct ExternalBfs {
+    rules: PyramideRules,
+    dir: PathBuf,
+    // seeds sorted in memory before a run is written
+    chunk: usize,
+    coups: Vec<Coup>,
+}
+
+struct SeedReader {
+    reader: BufReader<File>,
+    width: usize,
+}
+
+impl SeedReader {
+    fn open(path: &Path, width: usize) -> io::Result<SeedReader> {
+        Ok(SeedReader { reader: BufReader::new(File::open(path)?), width })
+    }
+
+    fn next_seed(&mut self) -> io::Result<Option<Seed>> {
+        let mut bytes = [0; 16];
+        match self.reader.read_exact(&mut bytes[..self.width]) {
+            Ok(()) => Ok(Some(Seed::from_le_bytes(bytes))),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Iterator for SeedReader {
+    type Item = io::Result<Seed>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_seed().transpose()
+    }
+}
+
+struct SeedWriter {
+    writer: BufWriter<File>,
+    width: usize,
+    count: u64,
+}
+
+impl SeedWriter {
+    fn create(path: &Path, width: usize) -> io::Result<SeedWriter> {
+        Ok(SeedWriter { writer: BufWriter::new(File::create(path)?), width, count: 0 })
+    }
+
+    fn write_seed(&mut self, seed: Seed) -> io::Result<()> {
+        self.count += 1;
+        self.writer.write_all(&seed.to_le_bytes()[..self.width])
+    }
+
+    fn finish(mut self) -> io::Result<u64> {
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+}
+
+impl ExternalBfs {
+    pub fn new(dir: impl Into<PathBuf>, rules: PyramideRules, chunk: usize) -> ExternalBfs {
+        assert!(chunk > 0);
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        ExternalBfs { rules, dir: dir.into(), chunk, coups }
+    }
+
+    fn width(&self) -> usize {
+        (self.rules.lines * self.rules.lines).div_ceil(8)
+    }
+
+    fn start_dir(&self, start: Seed) -> PathBuf {
+        self.dir.join(format!("bfs_{}_{}_{}", self.rules.lines, self.rules.diag_allowed, start))
+    }
+
+    pub fn layer_path(&self, start: Seed, pegs: u32) -> PathBuf {
+        self.start_dir(start).join(format!("layer_{}.seeds", pegs))
+    }
+
+    pub fn read_layer(&self, start: Seed, pegs: u32) -> io::Result<impl Iterator<Item = io::Result<Seed>> + use<>> {
+        // seeds of the layer in increasing order
+        SeedReader::open(&self.layer_path(start, pegs), self.width())
+    }
+
+    pub fn layer_size(&self, start: Seed, pegs: u32) -> io::Result<u64> {
+        Ok(fs::metadata(self.layer_path(start, pegs))?.len() / self.width() as u64)
+    }
+
+    pub fn run(&self, start: &Pyramide) -> io::Result<Vec<(u32, u64)>> {
+        // writes every layer, returns (pegs, positions) from the start to the last layer
+        let seed = start.seed();
+        let top = seed.count_ones();
+        fs::create_dir_all(self.start_dir(seed))?;
+        if !self.layer_path(seed, top).exists() {
+            self.write_layer(seed, top, [seed])?;
+        }
+        let mut pegs = top;
+        // resume after the last complete layer
+        while pegs > 1 && self.layer_path(seed, pegs - 1).exists() {
+            pegs -= 1;
+        }
+        while pegs > 1 && self.layer_size(seed, pegs)? > 0 {
+            self.expand(seed, pegs)?;
+            pegs -= 1;
+        }
+        (pegs..=top).rev()
+            .map(|pegs| Ok((pegs, self.layer_size(seed, pegs)?)))
+            .filter(|layer| !matches!(layer, Ok((_, 0))))
+            .collect()
+    }
+
+    fn write_layer(&self, start: Seed, pegs: u32, seeds: impl IntoIterator<Item = Seed>) -> io::Result<u64> {
+        // through a temporary file, the layer file only exists once complete
+        let tmp = self.start_dir(start).join(format!("layer_{}.tmp", pegs));
+        let mut writer = SeedWriter::create(&tmp, self.width())?;
+        for seed in seeds {
+            writer.write_seed(seed)?;
+        }
+        let count = writer.finish()?;
+        fs::rename(&tmp, self.layer_path(start, pegs))?;
+        Ok(count)
+    }
+
+    fn expand(&self, start: Seed, pegs: u32) -> io::Result<u64> {
+        // layer pegs - 1 from layer pegs
+        let mut runs: Vec<PathBuf> = vec![];
+        let mut chunk: Vec<Seed> = Vec::with_capacity(self.chunk);
+        for seed in self.read_layer(start, pegs)? {
+            let pyra = Pyramide::init_from_seed(self.rules.lines, seed?, self.rules.diag_allowed);
+            for coup in cherche_coups_possibles(&pyra, &self.coups).unwrap_or_default() {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(&coup).expect("already checked");
+                chunk.push(new_pyra.seed());
+                if chunk.len() >= self.chunk {
+                    runs.push(self.write_run(start, pegs - 1, runs.len(), &mut chunk)?);
+                }
+            }
+        }
+        if !chunk.is_empty() || runs.is_empty() {
+            runs.push(self.write_run(start, pegs - 1, runs.len(), &mut chunk)?);
+        }
+
+        // k-way merge of the sorted runs
+        let mut readers = runs.iter().map(|run| SeedReader::open(run, self.width())).collect::<io::Result<Vec<_>>>()?;
+        let mut heap = BinaryHeap::new();
+        for (idx, reader) in readers.iter_mut().enumerate() {
+            if let Some(seed) = reader.next_seed()? {
+                heap.push(Reverse((seed, idx)));
+            }
+        }
+        let tmp = self.start_dir(start).join(format!("layer_{}.tmp", pegs - 1));
+        let mut writer = SeedWriter::create(&tmp, self.width())?;
+        let mut last = None;
+        while let Some(Reverse((seed, idx))) = heap.pop() {
+            if last != Some(seed) {
+                writer.write_seed(seed)?;
+                last = Some(seed);
+            }
+            if let Some(next) = readers[idx].next_seed()? {
+                heap.push(Reverse((next, idx)));
+            }
+        }
+        let count = writer.finish()?;
+        fs::rename(&tmp, self.layer_path(start, pegs - 1))?;
+        for run in runs {
+            fs::remove_file(run)?;
+        }
+        Ok(count)
+    }
+
+    fn write_run(&self, start: Seed, pegs: u32, idx: usize, chunk: &mut Vec<Seed>) -> io::Result<PathBuf> {
+        chunk.sort_unstable();
+        chunk.dedup();
+        let path = self.start_dir(start).join(format!("layer_{}_run_{}.tmp", pegs, idx));
+        let mut writer = SeedWriter::create(&path, self.width())?;
+        for seed in chunk.drain(..) {
+            writer.write_seed(seed)?;
+        }
+        writer.finish()?;
+        Ok(path)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::checkpoint::test_dir;
+    use crate::dyn_pyra::state_graph::StateGraph;
+
+    #[test]
+    fn test_layers_match_state_graph() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << 5), true);
+        let graph = StateGraph::build_partial(vec![start.clone()]);
+        let sizes = graph.layer_sizes();
+
+        // small chunks, several runs by layer
+        let dir = test_dir("external_bfs");
+        let bfs = ExternalBfs::new(&dir, rules, 50);
+        let layers = bfs.run(&start).unwrap();
+        assert_eq!(layers[0], (15, 1));
+        for (pegs, size) in &layers {
+            assert_eq!(*size as usize, sizes[*pegs as usize]);
+        }
+        assert_eq!(layers.iter().map(|(_, size)| *size as usize).sum::<usize>(), graph.node_count());
+
+        let seeds: Vec<Seed> = bfs.read_layer(start.seed(), 12).unwrap().collect::<io::Result<_>>().unwrap();
+        assert!(seeds.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(seeds.iter().all(|seed| graph.index(*seed).is_some()));
+
+        // an interrupted exploration resumes after the last complete layer
+        fs::remove_file(bfs.layer_path(start.seed(), 3)).unwrap();
+        fs::remove_file(bfs.layer_path(start.seed(), 2)).unwrap();
+        assert_eq!(bfs.run(&start).unwrap(), layers);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
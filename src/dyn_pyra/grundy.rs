@@ -0,0 +1,151 @@
+// Sprague-Grundy values of the two-player game in normal play (the player who can not move
+// loses) : the value of a position is the mex of the values of the positions after each move,
+// the player to move wins when it is not 0. A position made of independent regions is a sum of
+// games, its value is the XOR of the values of its regions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use super::regions::Regions;
+use super::{cherche_coups_possibles, coups_theoriques, CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+pub type Grundy = u32;
+
+pub fn test_grundy(rules: PyramideRules) {
+    print!("{}", grundy_report(rules));
+}
+
+pub struct GrundySolver {
+    rules: PyramideRules,
+    coups: Vec<Coup>,
+    regions: Option<Regions>,
+    values: HashMap<Seed, Grundy>,
+    // positions split in several regions
+    pub decomposed: usize,
+}
+
+fn mex(values: &[Grundy]) -> Grundy {
+    (0..).find(|value| !values.contains(value)).expect("finite set")
+}
+
+impl GrundySolver {
+    pub fn new(rules: PyramideRules, decompose: bool) -> GrundySolver {
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        let regions = decompose.then(|| Regions::new(rules));
+        GrundySolver { rules, coups, regions, values: HashMap::new(), decomposed: 0 }
+    }
+
+    pub fn grundy(&mut self, seed: Seed) -> Grundy {
+        if let Some(value) = self.values.get(&seed) {
+            return *value
+        }
+        let split = self.regions.as_ref().map(|regions| regions.split(seed)).unwrap_or_default();
+        let value = if split.len() > 1 {
+            self.decomposed += 1;
+            split.into_iter().fold(0, |acc, region| acc ^ self.grundy(region))
+        } else {
+            let pyra = Pyramide::init_from_seed(self.rules.lines, seed, self.rules.diag_allowed);
+            let successors: Vec<Grundy> = cherche_coups_possibles(&pyra, &self.coups)
+                .unwrap_or_default()
+                .iter()
+                .map(|coup| {
+                    let mut new_pyra = pyra.clone();
+                    new_pyra.coup(coup).expect("already checked");
+                    self.grundy(new_pyra.seed())
+                })
+                .collect();
+            mex(&successors)
+        };
+        self.values.insert(seed, value);
+        return value
+    }
+
+    pub fn values(&self) -> &HashMap<Seed, Grundy> {
+        &self.values
+    }
+
+    pub fn reachable_distribution(&mut self, start: Seed) -> BTreeMap<Grundy, usize> {
+        // number of positions reachable from start for each Grundy value
+        let mut distribution = BTreeMap::new();
+        let mut seen: HashSet<Seed> = HashSet::new();
+        let mut to_visit = vec![Pyramide::init_from_seed(self.rules.lines, start, self.rules.diag_allowed)];
+        while let Some(pyra) = to_visit.pop() {
+            if !seen.insert(pyra.seed()) {
+                continue;
+            }
+            *distribution.entry(self.grundy(pyra.seed())).or_insert(0) += 1;
+            for coup in cherche_coups_possibles(&pyra, &self.coups).unwrap_or_default() {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(&coup).expect("already checked");
+                to_visit.push(new_pyra);
+            }
+        }
+        return distribution
+    }
+}
+
+pub fn grundy_report(rules: PyramideRules) -> String {
+    // Grundy value of each starting hole and distribution of the values of its reachable positions
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    let mut solver = GrundySolver::new(rules, true);
+    let mut report = String::new();
+    for hole in 0..cells_num as CellIndex {
+        let start = full_seed & !(1 << hole);
+        let distribution = solver.reachable_distribution(start);
+        let values: Vec<String> = distribution.iter().map(|(value, count)| format!("{}:{}", value, count)).collect();
+        writeln!(report, "Starting hole {} : grundy {} ({} wins), reachable values {}",
+            pyra.cell_name(hole),
+            solver.grundy(start),
+            if solver.grundy(start) == 0 { "second player" } else { "first player" },
+            values.join(" ")).unwrap();
+    }
+    writeln!(report, "{} positions solved, {} split in independent regions",
+        solver.values().len(), solver.decomposed).unwrap();
+    return report
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::state_graph::StateGraph;
+    use crate::dyn_pyra::two_player::{starting_hole_winners, PlayRule, Player};
+
+    #[test]
+    fn test_mex() {
+        assert_eq!(mex(&[]), 0);
+        assert_eq!(mex(&[0, 2, 1, 4]), 3);
+    }
+
+    #[test]
+    fn test_decomposition_gives_same_values() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let mut plain = GrundySolver::new(rules, false);
+        let mut decomposed = GrundySolver::new(rules, true);
+        for hole in [0, 4, 12] {
+            plain.grundy(0xFFFF & !(1 << hole));
+        }
+        for (seed, value) in plain.values() {
+            assert_eq!(decomposed.grundy(*seed), *value);
+        }
+        assert!(decomposed.decomposed > 0);
+    }
+
+    #[test]
+    fn test_grundy_matches_two_player() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let mut solver = GrundySolver::new(rules, true);
+        for (hole, winner) in starting_hole_winners(rules, PlayRule::Normal) {
+            let first_wins = solver.grundy(0xFFFF & !(1 << hole)) != 0;
+            assert_eq!(first_wins, winner == Player::First);
+        }
+        let distribution = solver.reachable_distribution(0xFFFF & !1);
+        let graph = StateGraph::build_partial(vec![Pyramide::init_from_seed(4, 0xFFFF & !1, true)]);
+        assert_eq!(distribution.values().sum::<usize>(), graph.node_count());
+        assert!(distribution.contains_key(&0));
+        let report = grundy_report(PyramideRules { lines: 3, diag_allowed: false });
+        assert_eq!(report.lines().count(), 10);
+    }
+}
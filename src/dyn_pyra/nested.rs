@@ -0,0 +1,297 @@
+// Nested Monte Carlo search, for the boards too large for an exhaustive search (8 lines and
+// more). A search of level n tries every legal move, scores it with a search of level n-1 from
+// the resulting position (level 0 is a random playout), and plays the next move of the best line
+// found so far. Every move removes a peg, the best line is the longest one.
+// The top level moves are played one step at a time within a budget, the search can be stopped
+// and resumed, in memory or through a checkpoint.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::checkpoint::{invalid_data, Checkpoint, Decoder, Encoder};
+use super::playouts::{MoveChoice, Playouts};
+use super::{cherche_coups_possibles, coups_theoriques, Coup, Pyramide, PyramideRules, Seed};
+
+const NESTED_SEARCH: &str = "nested_search";
+
+pub fn test_nested(rules: PyramideRules) -> io::Result<()> {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = Seed::MAX >> (Seed::BITS as usize - cells_num);
+    let start = Pyramide::init_from_seed(rules.lines, full_seed & !1, rules.diag_allowed);
+    let mut checkpoint = Checkpoint::new("checkpoints", rules, Duration::from_secs(60));
+    let mut search = NestedSearch::resume(&start, 2, 0, &checkpoint)?;
+    let budget = Budget { iterations: None, time: Some(Duration::from_secs(600)) };
+    let finished = search.run_with_checkpoint(budget, &mut checkpoint)?;
+    println!("{} playouts, best line {} pegs left{}", search.iterations, search.best_pegs(),
+        if finished { "" } else { " (stopped, run again to resume)" });
+    println!("{}", search.best_line_names().join(" "));
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    // number of playouts
+    pub iterations: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+pub struct NestedSearch {
+    start: Pyramide,
+    level: usize,
+    seed: u64,
+    coups: Vec<Coup>,
+    playouts: Playouts,
+    // moves played by the top level
+    prefix: Vec<Coup>,
+    // longest line found from start, it begins with prefix
+    best: Vec<Coup>,
+    pub iterations: u64,
+    // (playouts, time) limits of the current run
+    limits: (Option<u64>, Option<Instant>),
+    stopped: bool,
+}
+
+impl NestedSearch {
+    pub fn new(start: &Pyramide, level: usize, seed: u64) -> NestedSearch {
+        assert!(level >= 1, "level 0 is a single random playout");
+        let rules = start.rules();
+        NestedSearch {
+            start: start.clone(),
+            level,
+            seed,
+            coups: coups_theoriques(start),
+            playouts: Playouts::new(rules, MoveChoice::Uniform, seed),
+            prefix: vec![],
+            best: vec![],
+            iterations: 0,
+            limits: (None, None),
+            stopped: false,
+        }
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.start.rules()
+    }
+
+    pub fn best_line(&self) -> &[Coup] {
+        &self.best
+    }
+
+    pub fn best_line_names(&self) -> Vec<String> {
+        self.best.iter().filter_map(|coup| self.start.coup_name(coup)).collect()
+    }
+
+    pub fn best_pegs(&self) -> u32 {
+        self.start.seed().count_ones() - self.best.len() as u32
+    }
+
+    fn position(&self, coups: &[Coup]) -> Pyramide {
+        let mut pyra = self.start.clone();
+        for coup in coups {
+            pyra.coup(coup).expect("legal line");
+        }
+        pyra
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.best_pegs() == 1
+            || cherche_coups_possibles(&self.position(&self.prefix), &self.coups).is_none()
+    }
+
+    fn out_of_budget(&mut self) -> bool {
+        let (iterations, deadline) = self.limits;
+        self.stopped = self.stopped
+            || iterations.is_some_and(|limit| self.iterations >= limit)
+            || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        self.stopped
+    }
+
+    fn nested(&mut self, pyra: &Pyramide, level: usize) -> Vec<Coup> {
+        // longest line found from pyra
+        if level == 0 {
+            self.iterations += 1;
+            return self.playouts.playout(pyra).1
+        }
+        let pegs = pyra.seed().count_ones() as usize;
+        let mut pyra = pyra.clone();
+        let mut played: Vec<Coup> = vec![];
+        let mut best: Vec<Coup> = vec![];
+        while let Some(coups) = cherche_coups_possibles(&pyra, &self.coups) {
+            for coup in coups {
+                if self.out_of_budget() {
+                    return best
+                }
+                let mut child = pyra.clone();
+                child.coup(&coup).expect("legal move");
+                let tail = self.nested(&child, level - 1);
+                if played.len() + 1 + tail.len() > best.len() {
+                    best = [played.as_slice(), &[coup], &tail].concat();
+                    if best.len() == pegs - 1 {
+                        return best
+                    }
+                }
+            }
+            let next = best[played.len()];
+            pyra.coup(&next).expect("legal move");
+            played.push(next);
+        }
+        best
+    }
+
+    pub fn step(&mut self) -> bool {
+        // plays one top level move, false when the search is finished or out of budget
+        if self.is_finished() {
+            return false
+        }
+        let pyra = self.position(&self.prefix);
+        let coups = cherche_coups_possibles(&pyra, &self.coups).expect("not finished");
+        for coup in coups {
+            if self.out_of_budget() {
+                return false
+            }
+            let mut child = pyra.clone();
+            child.coup(&coup).expect("legal move");
+            let tail = self.nested(&child, self.level - 1);
+            if self.prefix.len() + 1 + tail.len() > self.best.len() {
+                self.best = [self.prefix.as_slice(), &[coup], &tail].concat();
+                if self.best_pegs() == 1 {
+                    break;
+                }
+            }
+        }
+        if self.stopped {
+            return false
+        }
+        self.prefix.push(self.best[self.prefix.len()]);
+        return true
+    }
+
+    pub fn run(&mut self, budget: Budget) -> bool {
+        // true when the search is finished, false when the budget ran out first
+        self.start_run(budget);
+        while self.step() {}
+        self.is_finished()
+    }
+
+    fn start_run(&mut self, budget: Budget) {
+        self.limits = (
+            budget.iterations.map(|iterations| self.iterations + iterations),
+            budget.time.map(|time| Instant::now() + time),
+        );
+        self.stopped = false;
+    }
+
+    pub fn run_with_checkpoint(&mut self, budget: Budget, checkpoint: &mut Checkpoint) -> io::Result<bool> {
+        self.start_run(budget);
+        while self.step() {
+            if checkpoint.is_due() {
+                checkpoint.save(NESTED_SEARCH, &self.encode())?;
+            }
+        }
+        if self.is_finished() {
+            checkpoint.clear(NESTED_SEARCH)?;
+            return Ok(true)
+        }
+        checkpoint.save(NESTED_SEARCH, &self.encode())?;
+        Ok(false)
+    }
+
+    fn header(start: &Pyramide, level: usize, seed: u64) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.seed(start.seed());
+        enc.u8(level as u8);
+        enc.u64(seed);
+        enc.finish()
+    }
+
+    fn coup_index(&self, coup: &Coup) -> u16 {
+        self.coups.iter().position(|c| c == coup).expect("a move of the rules") as u16
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.bytes(&NestedSearch::header(&self.start, self.level, self.seed));
+        enc.u64(self.iterations);
+        enc.u16s(&self.prefix.iter().map(|coup| self.coup_index(coup)).collect::<Vec<u16>>());
+        enc.u16s(&self.best.iter().map(|coup| self.coup_index(coup)).collect::<Vec<u16>>());
+        enc.finish()
+    }
+
+    pub fn resume(start: &Pyramide, level: usize, seed: u64, checkpoint: &Checkpoint) -> io::Result<NestedSearch> {
+        // search saved for the same start, level and seed, or a new search
+        let mut search = NestedSearch::new(start, level, seed);
+        if checkpoint.rules() != start.rules() {
+            return Err(invalid_data("checkpoint for other rules"))
+        }
+        let header = NestedSearch::header(start, level, seed);
+        let Some(data) = checkpoint.load(NESTED_SEARCH)?.filter(|data| data.starts_with(&header)) else {
+            return Ok(search)
+        };
+        let mut dec = Decoder::new(&data[header.len()..]);
+        search.iterations = dec.u64()?;
+        let line = |indexes: Vec<u16>| -> io::Result<Vec<Coup>> {
+            indexes.into_iter()
+                .map(|idx| search.coups.get(idx as usize).copied().ok_or(invalid_data("unknown move")))
+                .collect()
+        };
+        let prefix = line(dec.u16s()?)?;
+        let best = line(dec.u16s()?)?;
+        if !best.starts_with(&prefix) {
+            return Err(invalid_data("best line does not extend the played moves"))
+        }
+        let mut pyra = start.clone();
+        for coup in &best {
+            pyra.coup(coup).map_err(|_| invalid_data("illegal move in the saved line"))?;
+        }
+        search.prefix = prefix;
+        search.best = best;
+        // new random stream, the saved one can not be restored
+        search.playouts = Playouts::new(start.rules(), MoveChoice::Uniform, seed ^ search.iterations.rotate_left(32));
+        Ok(search)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::checkpoint::test_dir;
+
+    #[test]
+    fn test_nested_finds_a_win() {
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let mut search = NestedSearch::new(&start, 1, 3);
+        assert!(search.run(Budget::default()));
+        assert_eq!(search.best_pegs(), 1);
+        let end = search.position(search.best_line());
+        assert!(end.partie_gagne());
+        assert_eq!(search.best_line_names().len(), 14);
+    }
+
+    #[test]
+    fn test_stop_and_resume() {
+        let rules = PyramideRules { lines: 5, diag_allowed: false };
+        let start = Pyramide::init_from_seed(5, (1 << 25) - 1 - (1 << 6), false);
+        let dir = test_dir("nested");
+        let mut checkpoint = Checkpoint::new(&dir, rules, Duration::ZERO);
+
+        // in memory
+        let mut search = NestedSearch::new(&start, 1, 0);
+        assert!(!search.run(Budget { iterations: Some(30), time: None }));
+        let (prefix, iterations) = (search.prefix.clone(), search.iterations);
+        assert!(iterations >= 30 && !search.best.is_empty());
+
+        // through the checkpoint
+        assert!(!search.run_with_checkpoint(Budget { iterations: Some(30), time: None }, &mut checkpoint).unwrap());
+        assert!(search.prefix.starts_with(&prefix) && search.iterations > iterations);
+        let mut resumed = NestedSearch::resume(&start, 1, 0, &checkpoint).unwrap();
+        assert_eq!((&resumed.prefix, &resumed.best, resumed.iterations), (&search.prefix, &search.best, search.iterations));
+        // another seed starts over
+        assert_eq!(NestedSearch::resume(&start, 1, 1, &checkpoint).unwrap().iterations, 0);
+
+        assert!(resumed.run_with_checkpoint(Budget::default(), &mut checkpoint).unwrap());
+        assert!(resumed.best.len() >= search.best.len());
+        assert_eq!(checkpoint.load(NESTED_SEARCH).unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
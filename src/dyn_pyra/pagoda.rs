@@ -0,0 +1,247 @@
+// Pagoda functions : integer weights on the cells such that for every jump from a over b to c,
+// w(c) <= w(a) + w(b). The value of a position (sum of the weights of its pegs) can then never
+// increase, a position with a smaller value than a target can not reach it.
+// Pagodas are searched with a small integer program solved by microlp.
+
+use std::fmt;
+
+use microlp::{ComparisonOp, OptimizationDirection, Problem};
+
+use super::solver::Solver;
+use super::{jumps, Coup, Pyramide, PyramideRules, Seed};
+
+// weights are searched in -MAX_WEIGHT..=MAX_WEIGHT, small enough to be checked by hand
+const MAX_WEIGHT: i32 = 4;
+
+pub fn test_pagoda(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    for hole in 0..cells_num {
+        let start = full_seed & !(1 << hole);
+        if let Some(certificate) = certify_unsolvable(rules, start) {
+            print!("{}", certificate);
+            println!("Certificate checked : {}", certificate.check());
+            continue;
+        }
+        let pyra = Pyramide::init_from_seed(rules.lines, start, rules.diag_allowed);
+        let mut solver = Solver::new(&pyra, Some(PagodaPruning::new(rules, start)));
+        let solution = solver.solve(&pyra);
+        println!("Starting hole {} : {} ({} positions explored, {} pruned)",
+            pyra.cell_name(hole),
+            if solution.is_some() { "solvable" } else { "unsolvable" },
+            solver.explored, solver.pruned);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pagoda {
+    rules: PyramideRules,
+    weights: Vec<i32>,
+}
+
+impl Pagoda {
+    pub fn new(rules: PyramideRules, weights: Vec<i32>) -> Pagoda {
+        assert_eq!(weights.len(), rules.lines * rules.lines);
+        Pagoda { rules, weights }
+    }
+
+    pub fn value(&self, seed: Seed) -> i32 {
+        (0..self.weights.len()).filter(|i| seed & (1 << i) != 0).map(|i| self.weights[i]).sum()
+    }
+
+    pub fn violations(&self) -> Vec<Coup> {
+        jumps(self.rules).into_iter()
+            .filter(|(_, [dep, mil, arr])| self.weights[*arr] > self.weights[*dep] + self.weights[*mil])
+            .map(|(coup, _)| coup)
+            .collect()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations().is_empty()
+    }
+
+    fn fmt_weights(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self.rules.lines;
+        let mut header = " ".to_string();
+        for col in 1..=lines*2-1 {
+            header.push_str(&format!("{:>3}", col));
+        }
+        writeln!(f, "{}", header)?;
+        for row in 0..lines {
+            let mut line = format!("{}{}", (b'a' + row as u8) as char, "   ".repeat(lines - 1 - row));
+            for weight in &self.weights[row*row..(row+1)*(row+1)] {
+                line.push_str(&format!("{:>3}", weight));
+            }
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn best_pagoda(rules: PyramideRules, start: Seed, target: Seed) -> Pagoda {
+    // pagoda maximizing value(target) - value(start)
+    let cells_num = rules.lines * rules.lines;
+    let mut problem = Problem::new(OptimizationDirection::Maximize);
+    let vars: Vec<_> = (0..cells_num)
+        .map(|i| {
+            let coef = ((target >> i) & 1) as f64 - ((start >> i) & 1) as f64;
+            problem.add_integer_var(coef, (-MAX_WEIGHT, MAX_WEIGHT))
+        })
+        .collect();
+    for (_, [dep, mil, arr]) in jumps(rules) {
+        problem.add_constraint([(vars[arr], 1.0), (vars[dep], -1.0), (vars[mil], -1.0)], ComparisonOp::Le, 0.0);
+    }
+    // all zero weights are always feasible, the problem is bounded by the weight range
+    let solution = problem.solve().expect("feasible and bounded");
+    let weights = vars.iter().map(|var| solution.var_value_rounded(*var) as i32).collect();
+    Pagoda::new(rules, weights)
+}
+
+pub fn find_pagoda(rules: PyramideRules, start: Seed, target: Seed) -> Option<Pagoda> {
+    // a pagoda proving that target can not be reached from start
+    let pagoda = best_pagoda(rules, start, target);
+    (pagoda.value(target) > pagoda.value(start)).then_some(pagoda)
+}
+
+pub struct PagodaPruning {
+    // best pagoda for each single peg final position
+    pagodas: Vec<Pagoda>,
+}
+
+impl PagodaPruning {
+    pub fn new(rules: PyramideRules, start: Seed) -> PagodaPruning {
+        let pagodas = (0..rules.lines * rules.lines)
+            .map(|cell| best_pagoda(rules, start, 1 << cell))
+            .collect();
+        PagodaPruning { pagodas }
+    }
+
+    pub fn is_lost(&self, seed: Seed) -> bool {
+        // lost when no final cell can be reached according to one of the pagodas
+        let values: Vec<i32> = self.pagodas.iter().map(|p| p.value(seed)).collect();
+        (0..self.pagodas.len()).all(|cell| {
+            self.pagodas.iter().zip(&values).any(|(pagoda, value)| *value < pagoda.weights[cell])
+        })
+    }
+}
+
+// Proof that no single peg finish can be reached from start : a pagoda for each final cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    rules: PyramideRules,
+    start: Seed,
+    pagodas: Vec<Pagoda>,
+}
+
+pub fn certify_unsolvable(rules: PyramideRules, start: Seed) -> Option<Certificate> {
+    let pagodas = (0..rules.lines * rules.lines)
+        .map(|cell| find_pagoda(rules, start, 1 << cell))
+        .collect::<Option<Vec<Pagoda>>>()?;
+    Some(Certificate { rules, start, pagodas })
+}
+
+impl Certificate {
+    pub fn check(&self) -> bool {
+        self.pagodas.len() == self.rules.lines * self.rules.lines
+            && self.pagodas.iter().enumerate().all(|(cell, pagoda)| {
+                pagoda.rules == self.rules
+                    && pagoda.is_valid()
+                    && pagoda.value(self.start) < pagoda.weights[cell]
+            })
+    }
+}
+
+impl fmt::Display for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pyra = Pyramide::init_from_seed(self.rules.lines, self.start, self.rules.diag_allowed);
+        let empty: Vec<String> = (0..self.pagodas.len())
+            .filter(|i| self.start & (1 << i) == 0)
+            .map(|i| pyra.cell_name(i))
+            .collect();
+        writeln!(f, "Unsolvability certificate for {} lines {} diagonals, empty cells : {}",
+            self.rules.lines, if self.rules.diag_allowed { "with" } else { "without" }, empty.join(" "))?;
+        writeln!(f, "Each weighting below satisfies w(c) <= w(a) + w(b) for every jump from a over b to c,")?;
+        writeln!(f, "so the sum of the weights of the pegs never increases.")?;
+        for (cell, pagoda) in self.pagodas.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "Final peg on {} : start value {} < weight {}",
+                pyra.cell_name(cell), pagoda.value(self.start), pagoda.weights[cell])?;
+            pagoda.fmt_weights(f)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::retrograde::winnable_seeds;
+    use crate::dyn_pyra::CellIndex;
+
+    #[test]
+    fn test_is_valid() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        assert!(Pagoda::new(rules, vec![1; 16]).is_valid());
+        assert!(Pagoda::new(rules, vec![0; 16]).is_valid());
+        // a4 heavier than b4 + c4 breaks the vertical jump c4 -> a4
+        let mut weights = vec![0; 16];
+        weights[0] = 1;
+        let pagoda = Pagoda::new(rules, weights);
+        assert!(!pagoda.is_valid());
+        let pyra = Pyramide::new(4, false);
+        let violations: Vec<[CellIndex; 3]> = pagoda.violations().iter()
+            .map(|coup| pyra.coup_cells(coup).expect("on the board"))
+            .collect();
+        assert_eq!(violations, vec![[6, 2, 0]]);
+        assert_eq!(pagoda.value(0b111), 1);
+    }
+
+    #[test]
+    fn test_find_pagoda() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        for (start, target) in [(0b110, 0b1), (0xFFFE, 0b1 << 15)] {
+            if let Some(pagoda) = find_pagoda(rules, start, target) {
+                assert!(pagoda.is_valid());
+                assert!(pagoda.value(target) > pagoda.value(start));
+                assert!(pagoda.weights.iter().all(|w| w.abs() <= MAX_WEIGHT));
+            }
+        }
+        // b3 b4 -> a4 can not happen, b3 and b4 are on the same row as a4's neighbour
+        assert!(find_pagoda(rules, 0b110, 0b1).is_some());
+        // a single jump is always possible, no pagoda can forbid it
+        assert!(find_pagoda(rules, 0b101, 0b1 << 6).is_none());
+    }
+
+    #[test]
+    fn test_certificates_and_pruning_are_sound() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let winnable = winnable_seeds(rules);
+            for hole in 0..16 {
+                let start: Seed = 0xFFFF & !(1 << hole);
+                if let Some(certificate) = certify_unsolvable(rules, start) {
+                    assert!(certificate.check());
+                    assert!(!winnable.contains(start as usize));
+                }
+                let pruning = PagodaPruning::new(rules, start);
+                for seed in 1..0xFFFF_usize {
+                    if pruning.is_lost(seed as Seed) {
+                        assert!(!winnable.contains(seed));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_certificate_display() {
+        // 3 lines without diagonals, start with the top hole
+        let rules = PyramideRules { lines: 3, diag_allowed: false };
+        let certificate = certify_unsolvable(rules, 0b1_1111_1110).expect("unsolvable");
+        assert!(certificate.check());
+        let text = certificate.to_string();
+        assert!(text.contains("empty cells : a3"));
+        assert_eq!(text.matches("Final peg on").count(), 9);
+    }
+}
@@ -0,0 +1,253 @@
+// Number of winning games (move sequences ending with a single peg) from a position, by dynamic
+// programming over the state graph : a single peg position counts 1, any other position the sum
+// of the counts of its successors. Successors have one peg less, the layers are handled by
+// increasing number of pegs and only the previous layer is kept.
+// The counts quickly overflow u64 on larger boards, they are big integers.
+// A resumable count saves the counts of the last layer handled in a checkpoint.
+
+use std::collections::HashMap;
+use std::io;
+
+use num_bigint::BigUint;
+
+use super::checkpoint::{invalid_data, Checkpoint, Decoder, Encoder};
+use super::state_graph::{StateGraph, StateIndex};
+use super::{CellIndex, Pyramide, PyramideRules, Seed};
+
+pub fn test_winning_paths(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let starts: Vec<Pyramide> = (0..cells_num)
+        .map(|hole| Pyramide::init_from_seed(rules.lines, full_seed & !(1 << hole), rules.diag_allowed))
+        .collect();
+    let graph = StateGraph::build_partial(starts.clone());
+    let paths = WinningPaths::count(&graph, &starts.iter().map(Pyramide::seed).collect::<Vec<Seed>>());
+    for (hole, count) in paths.starts.iter().enumerate() {
+        println!("Starting hole {} : {} winning games", starts[0].cell_name(hole as CellIndex), count);
+    }
+    println!("Total : {} winning games", paths.total());
+    for (pegs, count) in paths.layers.iter().enumerate().skip(1) {
+        println!("{:>3} pegs : {} winning games from the positions of the graph", pegs, count);
+    }
+}
+
+const WINNING_PATHS: &str = "winning_paths";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WinningPaths {
+    // winning games from each start, in the order given
+    pub starts: Vec<BigUint>,
+    // layers[pegs] : winning games summed over the positions with pegs pegs
+    pub layers: Vec<BigUint>,
+}
+
+// counts of the layers handled so far
+struct Counting {
+    paths: WinningPaths,
+    pegs: usize,
+    // winning counts of the layer pegs, the positions without any are left out
+    previous: HashMap<StateIndex, BigUint>,
+}
+
+fn encode_count(enc: &mut Encoder, count: &BigUint) {
+    let bytes = count.to_bytes_le();
+    enc.u64(bytes.len() as u64);
+    enc.bytes(&bytes);
+}
+
+fn decode_count(dec: &mut Decoder) -> io::Result<BigUint> {
+    let len = dec.u64()? as usize;
+    Ok(BigUint::from_bytes_le(dec.bytes(len)?))
+}
+
+impl Counting {
+    fn new(cells_num: usize, starts: usize) -> Counting {
+        let paths = WinningPaths { starts: vec![BigUint::ZERO; starts], layers: vec![BigUint::ZERO; cells_num + 1] };
+        Counting { paths, pegs: 0, previous: HashMap::new() }
+    }
+
+    fn encode(&self, enc: &mut Encoder) {
+        enc.u64(self.pegs as u64);
+        for counts in [&self.paths.starts, &self.paths.layers] {
+            enc.u64(counts.len() as u64);
+            counts.iter().for_each(|count| encode_count(enc, count));
+        }
+        enc.u64(self.previous.len() as u64);
+        for (idx, count) in &self.previous {
+            enc.u32(*idx);
+            encode_count(enc, count);
+        }
+    }
+
+    fn decode(dec: &mut Decoder, cells_num: usize, starts: usize) -> io::Result<Counting> {
+        let pegs = dec.u64()? as usize;
+        let mut counts = [vec![], vec![]];
+        for counts in counts.iter_mut() {
+            let len = dec.u64()? as usize;
+            *counts = (0..len).map(|_| decode_count(dec)).collect::<io::Result<_>>()?;
+        }
+        let [starts_counts, layers] = counts;
+        if pegs > cells_num || starts_counts.len() != starts || layers.len() != cells_num + 1 {
+            return Err(invalid_data("inconsistent winning paths count"))
+        }
+        let len = dec.u64()? as usize;
+        let previous = (0..len).map(|_| Ok((dec.u32()?, decode_count(dec)?))).collect::<io::Result<_>>()?;
+        Ok(Counting { paths: WinningPaths { starts: starts_counts, layers }, pegs, previous })
+    }
+
+    fn run(mut self, graph: &StateGraph, starts: &[Seed], mut save: impl FnMut(&Counting) -> io::Result<()>) -> io::Result<WinningPaths> {
+        // layers above self.pegs, save is called after each one
+        let cells_num = graph.rules().lines * graph.rules().lines;
+        let mut layers_nodes: Vec<Vec<StateIndex>> = vec![vec![]; cells_num + 1];
+        for idx in 0..graph.node_count() as StateIndex {
+            layers_nodes[graph.seed(idx).count_ones() as usize].push(idx);
+        }
+        let top = starts.iter().map(|seed| seed.count_ones() as usize).max().unwrap_or(0);
+
+        for (pegs, nodes) in layers_nodes.iter().enumerate().take(top + 1).skip(self.pegs + 1) {
+            let mut current: HashMap<StateIndex, BigUint> = HashMap::new();
+            for &idx in nodes {
+                let count: BigUint = if pegs == 1 {
+                    BigUint::from(1_u32)
+                } else {
+                    graph.neighbors_slice(idx).iter().filter_map(|next| self.previous.get(next)).sum()
+                };
+                if count != BigUint::ZERO {
+                    self.paths.layers[pegs] += &count;
+                    current.insert(idx, count);
+                }
+            }
+            for (start, count) in starts.iter().zip(self.paths.starts.iter_mut()) {
+                if start.count_ones() as usize == pegs {
+                    let idx = graph.index(*start).expect("start in the graph");
+                    *count = current.get(&idx).cloned().unwrap_or_default();
+                }
+            }
+            self.previous = current;
+            self.pegs = pegs;
+            save(&self)?;
+        }
+        Ok(self.paths)
+    }
+}
+
+impl WinningPaths {
+    pub fn count(graph: &StateGraph, starts: &[Seed]) -> WinningPaths {
+        let cells_num = graph.rules().lines * graph.rules().lines;
+        Counting::new(cells_num, starts.len()).run(graph, starts, |_| Ok(())).expect("nothing to save")
+    }
+
+    pub fn count_resumable(graph: &StateGraph, starts: &[Seed], checkpoint: &mut Checkpoint) -> io::Result<WinningPaths> {
+        // The checkpoint holds the starts and the graph size, then the counts up to the last
+        // layer handled
+        if checkpoint.rules() != graph.rules() {
+            return Err(invalid_data("checkpoint for other rules"))
+        }
+        let cells_num = graph.rules().lines * graph.rules().lines;
+        let mut header = Encoder::new();
+        header.seeds(starts);
+        header.u64(graph.node_count() as u64);
+        let header = header.finish();
+
+        let counting = match checkpoint.load(WINNING_PATHS)?.filter(|data| data.starts_with(&header)) {
+            Some(data) => Counting::decode(&mut Decoder::new(&data[header.len()..]), cells_num, starts.len())?,
+            None => Counting::new(cells_num, starts.len()),
+        };
+        let paths = counting.run(graph, starts, |counting| {
+            if !checkpoint.is_due() {
+                return Ok(())
+            }
+            let mut enc = Encoder::new();
+            enc.bytes(&header);
+            counting.encode(&mut enc);
+            checkpoint.save(WINNING_PATHS, &enc.finish())
+        })?;
+        checkpoint.clear(WINNING_PATHS)?;
+        Ok(paths)
+    }
+
+    pub fn total(&self) -> BigUint {
+        self.starts.iter().sum()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::dyn_pyra::checkpoint::test_dir;
+    use crate::dyn_pyra::{cherche_coups_possibles, coups_theoriques};
+
+    fn count_by_recursion(pyra: &Pyramide, memo: &mut HashMap<Seed, u64>) -> u64 {
+        if pyra.partie_gagne() {
+            return 1
+        }
+        if let Some(count) = memo.get(&pyra.seed()) {
+            return *count
+        }
+        let coups = coups_theoriques(pyra);
+        let count = cherche_coups_possibles(pyra, &coups).unwrap_or_default().iter()
+            .map(|coup| {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(coup).expect("already checked");
+                count_by_recursion(&new_pyra, memo)
+            })
+            .sum();
+        memo.insert(pyra.seed(), count);
+        count
+    }
+
+    #[test]
+    fn test_count_matches_recursion() {
+        for diag_allowed in [false, true] {
+            let starts: Vec<Pyramide> = (0..16)
+                .map(|hole| Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), diag_allowed))
+                .collect();
+            let graph = StateGraph::build_partial(starts.clone());
+            let seeds: Vec<Seed> = starts.iter().map(Pyramide::seed).collect();
+            let paths = WinningPaths::count(&graph, &seeds);
+            let mut memo = HashMap::new();
+            for (start, count) in starts.iter().zip(&paths.starts) {
+                assert_eq!(*count, BigUint::from(count_by_recursion(start, &mut memo)));
+            }
+            assert_eq!(paths.total() != BigUint::ZERO, diag_allowed);
+            // every single peg position of the graph counts one
+            assert_eq!(paths.layers[1], BigUint::from(graph.layer_sizes()[1]));
+            assert_eq!(paths.layers[15], paths.total());
+        }
+    }
+
+    #[test]
+    fn test_count_resumes_from_checkpoint() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let starts: Vec<Pyramide> = [0, 5].iter().map(|hole| Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), true)).collect();
+        let graph = StateGraph::build_partial(starts.clone());
+        let seeds: Vec<Seed> = starts.iter().map(Pyramide::seed).collect();
+        let expected = WinningPaths::count(&graph, &seeds);
+
+        // interrupted after the layer of 6 pegs
+        let dir = test_dir("winning_paths");
+        let mut checkpoint = Checkpoint::new(&dir, rules, Duration::ZERO);
+        let mut header = Encoder::new();
+        header.seeds(&seeds);
+        header.u64(graph.node_count() as u64);
+        let header = header.finish();
+        let interrupted = Counting::new(16, seeds.len()).run(&graph, &seeds, |counting| {
+            let mut enc = Encoder::new();
+            enc.bytes(&header);
+            counting.encode(&mut enc);
+            checkpoint.save(WINNING_PATHS, &enc.finish())?;
+            if counting.pegs == 6 { Err(io::Error::other("interrupted")) } else { Ok(()) }
+        });
+        assert!(interrupted.is_err());
+        let data = checkpoint.load(WINNING_PATHS).unwrap().expect("saved");
+        let saved = Counting::decode(&mut Decoder::new(&data[header.len()..]), 16, seeds.len()).unwrap();
+        assert_eq!((saved.pegs, &saved.paths.layers[..7]), (6, &expected.layers[..7]));
+
+        assert_eq!(WinningPaths::count_resumable(&graph, &seeds, &mut checkpoint).unwrap(), expected);
+        assert_eq!(checkpoint.load(WINNING_PATHS).unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
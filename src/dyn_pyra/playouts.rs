@@ -0,0 +1,203 @@
+// Random playouts, for the boards too large to enumerate : games are played from a start with
+// random legal moves until no move is left. The distribution of the final number of pegs gives
+// an estimate of the probability that a random player wins.
+
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::SeedableRng;
+
+use super::{cherche_coups_possibles, coups_theoriques, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_playouts(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = Seed::MAX >> (Seed::BITS as usize - cells_num);
+    let start = Pyramide::init_from_seed(rules.lines, full_seed & !1, rules.diag_allowed);
+    for choice in [MoveChoice::Uniform, MoveChoice::Weighted(mobility_weight)] {
+        let stats = Playouts::new(rules, choice, 0).run(&start, 10_000);
+        println!("{}", stats);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MoveChoice {
+    Uniform,
+    // probability of a legal move proportional to its weight (position before the move), uniform
+    // when no weight is usable (all zero, or a negative or NaN weight)
+    Weighted(fn(&Pyramide, &Coup) -> f64),
+}
+
+pub fn mobility_weight(pyra: &Pyramide, coup: &Coup) -> f64 {
+    // favours the moves leaving many moves to play
+    let mut new_pyra = pyra.clone();
+    new_pyra.coup(coup).expect("legal move");
+    let coups = coups_theoriques(&new_pyra);
+    1.0 + cherche_coups_possibles(&new_pyra, &coups).map_or(0, |coups| coups.len()) as f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayoutStats {
+    pub games: usize,
+    // games ending with final_pegs[n] pegs left
+    pub final_pegs: Vec<usize>,
+    // fewest pegs left, with its moves
+    pub best: Vec<Coup>,
+    pub best_pegs: u32,
+}
+
+impl PlayoutStats {
+    pub fn wins(&self) -> usize {
+        self.final_pegs.get(1).copied().unwrap_or(0)
+    }
+
+    pub fn win_probability(&self) -> f64 {
+        // 0 without any game
+        if self.games == 0 {
+            return 0.0
+        }
+        self.wins() as f64 / self.games as f64
+    }
+
+    pub fn standard_error(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0
+        }
+        let p = self.win_probability();
+        (p * (1.0 - p) / self.games as f64).sqrt()
+    }
+}
+
+impl fmt::Display for PlayoutStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} games, win probability {:.5} (+/- {:.5}), best game {} pegs",
+            self.games, self.win_probability(), self.standard_error(), self.best_pegs)?;
+        for (pegs, count) in self.final_pegs.iter().enumerate().filter(|(_, count)| **count > 0) {
+            writeln!(f, "{:>3} pegs : {}", pegs, count)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Playouts {
+    rules: PyramideRules,
+    coups: Vec<Coup>,
+    choice: MoveChoice,
+    rng: StdRng,
+}
+
+impl Playouts {
+    pub fn new(rules: PyramideRules, choice: MoveChoice, seed: u64) -> Playouts {
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        Playouts { rules, coups, choice, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.rules
+    }
+
+    pub fn choose(&mut self, pyra: &Pyramide) -> Option<Coup> {
+        let coups = cherche_coups_possibles(pyra, &self.coups)?;
+        let coup = match self.choice {
+            MoveChoice::Uniform => coups.choose(&mut self.rng),
+            MoveChoice::Weighted(weight) => match coups.choose_weighted(&mut self.rng, |coup| weight(pyra, coup)) {
+                Ok(coup) => Some(coup),
+                Err(_) => coups.choose(&mut self.rng),
+            },
+        };
+        coup.copied()
+    }
+
+    pub fn playout(&mut self, start: &Pyramide) -> (Pyramide, Vec<Coup>) {
+        // final position and moves of one random game
+        let mut pyra = start.clone();
+        let mut coups = vec![];
+        while let Some(coup) = self.choose(&pyra) {
+            pyra.coup(&coup).expect("legal move");
+            coups.push(coup);
+        }
+        (pyra, coups)
+    }
+
+    pub fn run(&mut self, start: &Pyramide, games: usize) -> PlayoutStats {
+        let mut stats = PlayoutStats {
+            games,
+            final_pegs: vec![0; self.rules.lines * self.rules.lines + 1],
+            best: vec![],
+            best_pegs: start.seed().count_ones(),
+        };
+        for _ in 0..games {
+            let (end, coups) = self.playout(start);
+            let pegs = end.seed().count_ones();
+            stats.final_pegs[pegs as usize] += 1;
+            if pegs < stats.best_pegs {
+                stats.best_pegs = pegs;
+                stats.best = coups;
+            }
+        }
+        stats
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let stats = Playouts::new(rules, MoveChoice::Uniform, 7).run(&start, 500);
+        assert_eq!(stats.final_pegs.iter().sum::<usize>(), 500);
+        assert_eq!(stats.final_pegs[0], 0);
+        assert!(stats.wins() > 0);
+        assert!(stats.standard_error() > 0.0);
+
+        // the best game is replayable
+        let mut pyra = start.clone();
+        for coup in &stats.best {
+            pyra.coup(coup).expect("legal move");
+        }
+        assert_eq!(pyra.seed().count_ones(), stats.best_pegs);
+
+        // same seed, same games
+        assert_eq!(Playouts::new(rules, MoveChoice::Uniform, 7).run(&start, 500), stats);
+        let weighted = Playouts::new(rules, MoveChoice::Weighted(mobility_weight), 7).run(&start, 500);
+        assert_eq!(weighted.final_pegs.iter().sum::<usize>(), 500);
+    }
+
+    #[test]
+    fn test_no_win_without_diagonals() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << 5), false);
+        let stats = Playouts::new(rules, MoveChoice::Uniform, 0).run(&start, 200);
+        assert_eq!(stats.wins(), 0);
+        assert_eq!(stats.win_probability(), 0.0);
+
+        let stats = Playouts::new(rules, MoveChoice::Uniform, 0).run(&start, 0);
+        assert_eq!((stats.win_probability(), stats.standard_error()), (0.0, 0.0));
+    }
+    #[test]
+    fn test_unusable_weights() {
+        // the games still go on until no move is left
+        fn zero_weight(_: &Pyramide, _: &Coup) -> f64 {
+            0.0
+        }
+        fn nan_weight(_: &Pyramide, _: &Coup) -> f64 {
+            f64::NAN
+        }
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let coups = coups_theoriques(&start);
+        for weight in [zero_weight, nan_weight] {
+            let mut playouts = Playouts::new(rules, MoveChoice::Weighted(weight), 3);
+            for _ in 0..50 {
+                let (end, moves) = playouts.playout(&start);
+                assert!(!moves.is_empty());
+                assert!(cherche_coups_possibles(&end, &coups).is_none());
+            }
+            assert!(playouts.run(&start, 500).wins() > 0);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// Puzzle generator : positions with a given number of pegs and few solutions. Candidates are
+// built backwards from a single peg with random un-jumps, so they are always solvable, then
+// kept when their number of solutions is small enough.
+// The difficulty is -log2 of the probability that a player choosing each move at random wins,
+// the branching factor and the proportion of losing moves are measured along the solution.
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+
+use super::record::GameRecord;
+use super::solver::Solver;
+use super::{cherche_coups_inverses, cherche_coups_possibles, coups_theoriques, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_puzzle(rules: PyramideRules) {
+    let mut generator = PuzzleGenerator::new(rules, 0);
+    for pegs in 6..=10 {
+        if let Some(puzzle) = generator.generate(pegs, 1, 50) {
+            println!("{}", puzzle.record());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    // number of winning move sequences
+    pub solutions: BigUint,
+    pub solution_length: usize,
+    // mean number of legal moves along the solution
+    pub branching: f64,
+    // losing moves / legal moves along the solution
+    pub losing_ratio: f64,
+    // probability to win playing random legal moves
+    pub random_win: f64,
+    pub difficulty: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub start: Pyramide,
+    pub solution: Vec<Coup>,
+    pub rating: Rating,
+}
+
+impl Puzzle {
+    pub fn record(&self) -> GameRecord {
+        GameRecord::new(&self.start, self.solution.clone())
+            .with_tag("Pegs", self.start.seed().count_ones())
+            .with_tag("Solutions", &self.rating.solutions)
+            .with_tag("Branching", format!("{:.2}", self.rating.branching))
+            .with_tag("LosingMoves", format!("{:.2}", self.rating.losing_ratio))
+            .with_tag("Difficulty", format!("{:.2}", self.rating.difficulty))
+    }
+}
+
+// solution counts and random play win probabilities, by seed
+struct Analysis<'a> {
+    rules: PyramideRules,
+    coups: &'a [Coup],
+    solutions: HashMap<Seed, BigUint>,
+    random_win: HashMap<Seed, f64>,
+}
+
+impl Analysis<'_> {
+    fn successors(&self, seed: Seed) -> Vec<Seed> {
+        let pyra = Pyramide::init_from_seed(self.rules.lines, seed, self.rules.diag_allowed);
+        cherche_coups_possibles(&pyra, self.coups)
+            .unwrap_or_default()
+            .iter()
+            .map(|coup| {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(coup).expect("already checked");
+                new_pyra.seed()
+            })
+            .collect()
+    }
+
+    fn solutions(&mut self, seed: Seed) -> BigUint {
+        if seed.count_ones() == 1 {
+            return BigUint::from(1_u32)
+        }
+        if let Some(count) = self.solutions.get(&seed) {
+            return count.clone()
+        }
+        let count = self.successors(seed).into_iter().map(|next| self.solutions(next)).sum::<BigUint>();
+        self.solutions.insert(seed, count.clone());
+        return count
+    }
+
+    fn random_win(&mut self, seed: Seed) -> f64 {
+        if seed.count_ones() == 1 {
+            return 1.0
+        }
+        if let Some(prob) = self.random_win.get(&seed) {
+            return *prob
+        }
+        let successors = self.successors(seed);
+        let prob = if successors.is_empty() {
+            0.0
+        } else {
+            successors.iter().map(|next| self.random_win(*next)).sum::<f64>() / successors.len() as f64
+        };
+        self.random_win.insert(seed, prob);
+        return prob
+    }
+}
+
+pub fn rate(start: &Pyramide, solution: &[Coup]) -> Rating {
+    let coups = coups_theoriques(start);
+    let mut analysis = Analysis { rules: start.rules(), coups: &coups, solutions: HashMap::new(), random_win: HashMap::new() };
+    let (mut legal, mut losing) = (0, 0);
+    let mut pyra = start.clone();
+    for coup in solution {
+        let successors = analysis.successors(pyra.seed());
+        legal += successors.len();
+        losing += successors.into_iter().filter(|next| analysis.solutions(*next) == BigUint::ZERO).count();
+        pyra.coup(coup).expect("valid solution");
+    }
+    let random_win = analysis.random_win(start.seed());
+    Rating {
+        solutions: analysis.solutions(start.seed()),
+        solution_length: solution.len(),
+        branching: legal as f64 / solution.len().max(1) as f64,
+        losing_ratio: losing as f64 / legal.max(1) as f64,
+        random_win,
+        difficulty: (1.0 / random_win).log2(),
+    }
+}
+
+pub struct PuzzleGenerator {
+    rules: PyramideRules,
+    coups: Vec<Coup>,
+    rng: StdRng,
+}
+
+impl PuzzleGenerator {
+    pub fn new(rules: PyramideRules, seed: u64) -> PuzzleGenerator {
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        PuzzleGenerator { rules, coups, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    fn candidate(&mut self, pegs: usize) -> Option<Pyramide> {
+        // random un-jumps from a random single peg
+        let cell = self.rng.random_range(0..self.rules.lines * self.rules.lines);
+        let mut pyra = Pyramide::init_from_seed(self.rules.lines, 1 << cell, self.rules.diag_allowed);
+        for _ in 1..pegs {
+            let coup = *cherche_coups_inverses(&pyra, &self.coups).choose(&mut self.rng)?;
+            pyra.coup_inverse(&coup).expect("already checked");
+        }
+        Some(pyra)
+    }
+
+    pub fn generate(&mut self, pegs: usize, max_solutions: u64, attempts: usize) -> Option<Puzzle> {
+        // best rated puzzle with at most max_solutions solutions among the candidates
+        let mut best: Option<Puzzle> = None;
+        for _ in 0..attempts {
+            let Some(start) = self.candidate(pegs) else {
+                continue;
+            };
+            let solution = Solver::new(&start, None).solve(&start).expect("built from a won position");
+            let rating = rate(&start, &solution);
+            if rating.solutions > BigUint::from(max_solutions) {
+                continue;
+            }
+            if best.as_ref().is_none_or(|puzzle| puzzle.rating.difficulty < rating.difficulty) {
+                best = Some(Puzzle { start, solution, rating });
+            }
+        }
+        return best
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        // a single move left
+        let start = Pyramide::init_from_seed(3, 0b1_1000_0000, false);
+        let solution = Solver::new(&start, None).solve(&start).expect("solvable");
+        let rating = rate(&start, &solution);
+        assert_eq!((rating.solutions, rating.solution_length), (BigUint::from(1_u32), 1));
+        assert_eq!((rating.random_win, rating.difficulty), (1.0, 0.0));
+
+        // b3 c1 c3 c4 : c3-a3 and c3-c5 lose at once, c4-c2 then c1-c3 and c3-a3 are forced
+        let start = Pyramide::init_from_seed(3, 0b1101_0100, false);
+        let solution = Solver::new(&start, None).solve(&start).expect("solvable");
+        let names: Vec<String> = {
+            let mut pyra = start.clone();
+            solution.iter().map(|coup| {
+                let name = pyra.coup_name(coup).expect("on the board");
+                pyra.coup(coup).expect("valid solution");
+                name
+            }).collect()
+        };
+        assert_eq!(names, ["c4-c2", "c1-c3", "c3-a3"]);
+        let rating = rate(&start, &solution);
+        assert_eq!((rating.solutions, rating.solution_length), (BigUint::from(1_u32), 3));
+        // 3 + 1 + 1 legal moves, 2 of them losing
+        assert_eq!((rating.branching, rating.losing_ratio), (5.0 / 3.0, 0.4));
+        assert_eq!(rating.random_win, 1.0 / 3.0);
+        assert!((rating.difficulty - 3.0_f64.log2()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_generate() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let mut generator = PuzzleGenerator::new(rules, 42);
+        let puzzle = generator.generate(7, 1, 50).expect("a unique solution puzzle");
+        assert_eq!(puzzle.start.seed().count_ones(), 7);
+        assert_eq!(puzzle.rating.solutions, BigUint::from(1_u32));
+        assert_eq!(puzzle.solution.len(), 6);
+        assert!(puzzle.rating.random_win > 0.0 && puzzle.rating.random_win < 1.0);
+
+        let record = GameRecord::parse(&puzzle.record().to_string()).expect("valid record");
+        assert_eq!(record.start, puzzle.start.seed());
+        assert_eq!(record.coups, puzzle.solution);
+        assert_eq!(record.tag("Solutions"), Some("1"));
+        assert!(record.end().expect("legal moves").partie_gagne());
+
+        // same seed, same puzzle
+        assert_eq!(PuzzleGenerator::new(rules, 42).generate(7, 1, 50), Some(puzzle));
+    }
+}
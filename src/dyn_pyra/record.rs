@@ -0,0 +1,156 @@
+// Game records, a text format close to PGN : tag pairs, then the numbered moves by name.
+//   [Lines "4"]
+//   [Diagonals "true"]
+//   [Start "65534"]
+//   [Difficulty "5.32"]
+//
+//   1. c4-a4 2. d2-b4 3. ...
+// Lines, Diagonals and Start (seed of the starting position) are required, any other tag is kept.
+
+use std::fmt;
+
+use super::{Coup, Pyramide, PyramideRules, Seed};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub rules: PyramideRules,
+    pub start: Seed,
+    pub coups: Vec<Coup>,
+    // other tags, in order
+    pub tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordError {
+    BadTag(String),
+    MissingTag(&'static str),
+    BadValue(&'static str),
+    IllegalMove(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::BadTag(line) => write!(f, "malformed tag {}", line),
+            RecordError::MissingTag(tag) => write!(f, "missing tag {}", tag),
+            RecordError::BadValue(tag) => write!(f, "bad value for tag {}", tag),
+            RecordError::IllegalMove(name) => write!(f, "illegal move {}", name),
+        }
+    }
+}
+
+impl GameRecord {
+    pub fn new(start: &Pyramide, coups: Vec<Coup>) -> GameRecord {
+        GameRecord { rules: start.rules(), start: start.seed(), coups, tags: vec![] }
+    }
+
+    pub fn with_tag(mut self, name: &str, value: impl ToString) -> GameRecord {
+        self.tags.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn start(&self) -> Pyramide {
+        Pyramide::init_from_seed(self.rules.lines, self.start, self.rules.diag_allowed)
+    }
+
+    pub fn end(&self) -> Result<Pyramide, ()> {
+        // position after all the moves
+        let mut pyra = self.start();
+        for coup in &self.coups {
+            pyra.coup(coup)?;
+        }
+        Ok(pyra)
+    }
+
+    pub fn parse(text: &str) -> Result<GameRecord, RecordError> {
+        let mut tags: Vec<(String, String)> = vec![];
+        let mut moves: Vec<&str> = vec![];
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if line.starts_with('[') {
+                let tag = line.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+                    .and_then(|l| l.split_once(' '))
+                    .and_then(|(name, value)| Some((name, value.trim().strip_prefix('"')?.strip_suffix('"')?)))
+                    .ok_or(RecordError::BadTag(line.to_string()))?;
+                tags.push((tag.0.to_string(), tag.1.to_string()));
+            } else {
+                // move numbers end with a dot
+                moves.extend(line.split_whitespace().filter(|word| !word.ends_with('.')));
+            }
+        }
+
+        let mut take = |name: &'static str| -> Result<String, RecordError> {
+            let pos = tags.iter().position(|(tag, _)| tag == name).ok_or(RecordError::MissingTag(name))?;
+            Ok(tags.remove(pos).1)
+        };
+        let lines: usize = take("Lines")?.parse().map_err(|_| RecordError::BadValue("Lines"))?;
+        if !(1..=11).contains(&lines) {
+            return Err(RecordError::BadValue("Lines"))
+        }
+        let diag_allowed: bool = take("Diagonals")?.parse().map_err(|_| RecordError::BadValue("Diagonals"))?;
+        let start: Seed = take("Start")?.parse().map_err(|_| RecordError::BadValue("Start"))?;
+        if lines * lines < Seed::BITS as usize && start >> (lines * lines) != 0 {
+            return Err(RecordError::BadValue("Start"))
+        }
+
+        let rules = PyramideRules { lines, diag_allowed };
+        let mut pyra = Pyramide::init_from_seed(lines, start, diag_allowed);
+        let mut coups = vec![];
+        for name in moves {
+            let coup = pyra.coup_from_name(name).ok_or(RecordError::IllegalMove(name.to_string()))?;
+            pyra.coup(&coup).map_err(|_| RecordError::IllegalMove(name.to_string()))?;
+            coups.push(coup);
+        }
+        Ok(GameRecord { rules, start, coups, tags })
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Lines \"{}\"]", self.rules.lines)?;
+        writeln!(f, "[Diagonals \"{}\"]", self.rules.diag_allowed)?;
+        writeln!(f, "[Start \"{}\"]", self.start)?;
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        writeln!(f)?;
+        let pyra = self.start();
+        let moves: Vec<String> = self.coups.iter().enumerate()
+            .map(|(i, coup)| format!("{}. {}", i + 1, pyra.coup_name(coup).unwrap_or_default()))
+            .collect();
+        writeln!(f, "{}", moves.join(" "))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::solver::Solver;
+
+    #[test]
+    fn test_record_round_trip() {
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let solution = Solver::new(&start, None).solve(&start).expect("solvable");
+        let record = GameRecord::new(&start, solution).with_tag("Event", "test");
+        let text = record.to_string();
+        assert!(text.starts_with("[Lines \"4\"]\n[Diagonals \"true\"]\n[Start \"65534\"]\n[Event \"test\"]\n\n1. "));
+        let parsed = GameRecord::parse(&text).expect("valid record");
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.tag("Event"), Some("test"));
+        assert!(parsed.end().expect("legal moves").partie_gagne());
+    }
+
+    #[test]
+    fn test_record_errors() {
+        assert_eq!(GameRecord::parse("[Lines \"4\"]\n[Diagonals \"true\"]\n"), Err(RecordError::MissingTag("Start")));
+        assert_eq!(GameRecord::parse("[Lines 4]"), Err(RecordError::BadTag("[Lines 4]".to_string())));
+        assert_eq!(
+            GameRecord::parse("[Lines \"4\"]\n[Diagonals \"false\"]\n[Start \"65534\"]\n1. c2-a4"),
+            Err(RecordError::IllegalMove("c2-a4".to_string())),
+        );
+    }
+}
@@ -0,0 +1,201 @@
+// Solitaire analysis by independent regions (see regions.rs). The moves of a position made of
+// several regions are the moves of its regions, each region is played out on its own : the
+// fewest pegs that can be left is the sum over the regions. A region always keeps a peg (a jump
+// needs two), so a position split in two regions or more can never be won, whatever is left
+// to play : the search stops there, and the split explains the loss.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::regions::Regions;
+use super::{cherche_coups_possibles, coups_theoriques, CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_region_solver(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    let mut plain = RegionSolver::new(rules, false);
+    let mut decomposed = RegionSolver::new(rules, true);
+    for hole in 0..cells_num as CellIndex {
+        let start = full_seed & !(1 << hole);
+        println!("Starting hole {} : {}", pyra.cell_name(hole),
+            if decomposed.is_winnable(start) { "solvable" } else { "unsolvable" });
+        plain.is_winnable(start);
+    }
+    println!("{} positions explored, {} without decomposition, {} searches stopped by a split",
+        decomposed.explored, plain.explored, decomposed.decomposed);
+    let lost_start = (0..cells_num).map(|hole| full_seed & !(1 << hole)).find(|start| !decomposed.is_winnable(*start));
+    if let Some(start) = lost_start {
+        // a dead end of the best line, and why it is lost
+        let mut seed = start;
+        while let Some(next) = decomposed.best_successor(seed) {
+            seed = next;
+        }
+        print!("{}", decomposed.explain(seed));
+    }
+}
+
+pub struct RegionSolver {
+    rules: PyramideRules,
+    coups: Vec<Coup>,
+    regions: Option<Regions>,
+    winnable: HashMap<Seed, bool>,
+    best: HashMap<Seed, u32>,
+    pub explored: usize,
+    // positions answered from their regions
+    pub decomposed: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionsExplanation {
+    rules: PyramideRules,
+    // pegs of each region, with the fewest pegs it can be left with
+    pub regions: Vec<(Seed, u32)>,
+    pub best_pegs: u32,
+}
+
+impl RegionSolver {
+    pub fn new(rules: PyramideRules, decompose: bool) -> RegionSolver {
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        let regions = decompose.then(|| Regions::new(rules));
+        RegionSolver { rules, coups, regions, winnable: HashMap::new(), best: HashMap::new(), explored: 0, decomposed: 0 }
+    }
+
+    fn split(&self, seed: Seed) -> Vec<Seed> {
+        self.regions.as_ref().map(|regions| regions.split(seed)).unwrap_or_else(|| vec![seed])
+    }
+
+    fn successors(&self, seed: Seed) -> Vec<Seed> {
+        let pyra = Pyramide::init_from_seed(self.rules.lines, seed, self.rules.diag_allowed);
+        cherche_coups_possibles(&pyra, &self.coups)
+            .unwrap_or_default()
+            .iter()
+            .map(|coup| {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(coup).expect("already checked");
+                new_pyra.seed()
+            })
+            .collect()
+    }
+
+    pub fn is_winnable(&mut self, seed: Seed) -> bool {
+        if seed.count_ones() == 1 {
+            return true
+        }
+        if let Some(winnable) = self.winnable.get(&seed) {
+            return *winnable
+        }
+        self.explored += 1;
+        let winnable = if self.split(seed).len() > 1 {
+            self.decomposed += 1;
+            false
+        } else {
+            self.successors(seed).into_iter().any(|next| self.is_winnable(next))
+        };
+        self.winnable.insert(seed, winnable);
+        return winnable
+    }
+
+    pub fn best_pegs(&mut self, seed: Seed) -> u32 {
+        // fewest pegs that can be left
+        if let Some(best) = self.best.get(&seed) {
+            return *best
+        }
+        self.explored += 1;
+        let split = self.split(seed);
+        let best = if split.len() > 1 {
+            self.decomposed += 1;
+            split.into_iter().map(|region| self.best_pegs(region)).sum()
+        } else {
+            let successors = self.successors(seed);
+            successors.into_iter().map(|next| self.best_pegs(next)).min().unwrap_or(seed.count_ones())
+        };
+        self.best.insert(seed, best);
+        return best
+    }
+
+    pub fn best_successor(&mut self, seed: Seed) -> Option<Seed> {
+        // a position after a move keeping the fewest pegs that can be left
+        let best = self.best_pegs(seed);
+        self.successors(seed).into_iter().find(|next| self.best_pegs(*next) == best)
+    }
+
+    pub fn explain(&mut self, seed: Seed) -> RegionsExplanation {
+        let regions: Vec<(Seed, u32)> = self.split(seed).into_iter().map(|region| (region, self.best_pegs(region))).collect();
+        let best_pegs = regions.iter().map(|(_, best)| best).sum();
+        RegionsExplanation { rules: self.rules, regions, best_pegs }
+    }
+}
+
+impl RegionsExplanation {
+    pub fn is_lost(&self) -> bool {
+        self.best_pegs > 1
+    }
+}
+
+impl fmt::Display for RegionsExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pyra = Pyramide::new(self.rules.lines, self.rules.diag_allowed);
+        let names = |pegs: Seed| -> String {
+            let cells: Vec<String> = (0..self.rules.lines * self.rules.lines)
+                .filter(|cell| pegs & (1 << cell) != 0)
+                .map(|cell| pyra.cell_name(cell))
+                .collect();
+            cells.join(" ")
+        };
+        match (self.regions.len(), self.is_lost()) {
+            (0, _) => writeln!(f, "Empty board")?,
+            (1, false) => writeln!(f, "Winnable")?,
+            (1, true) => writeln!(f, "Lost : a single region, at best {} pegs left", self.best_pegs)?,
+            (regions, _) => writeln!(f, "Lost : {} independent regions, each keeps at least one peg, at best {} pegs left",
+                regions, self.best_pegs)?,
+        }
+        for (pegs, best) in &self.regions {
+            writeln!(f, "  {} : at best {} pegs", names(*pegs), best)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::retrograde::winnable_seeds;
+    use crate::dyn_pyra::tablebase::Tablebase;
+
+    #[test]
+    fn test_isolated_pegs() {
+        let mut solver = RegionSolver::new(PyramideRules { lines: 4, diag_allowed: false }, true);
+        // a4 and d7, far apart
+        let seed = 1 | (1 << 15);
+        assert!(!solver.is_winnable(seed));
+        let explanation = solver.explain(seed);
+        assert_eq!(explanation.regions, vec![(1, 1), (1 << 15, 1)]);
+        assert!(explanation.is_lost());
+        assert_eq!(explanation.to_string(),
+            "Lost : 2 independent regions, each keeps at least one peg, at best 2 pegs left\n  a4 : at best 1 pegs\n  d7 : at best 1 pegs\n");
+    }
+
+    #[test]
+    fn test_matches_exhaustive_results() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let winnable = winnable_seeds(rules);
+            let tablebase = Tablebase::build(rules);
+            let mut plain = RegionSolver::new(rules, false);
+            let mut decomposed = RegionSolver::new(rules, true);
+            for hole in 0..16 {
+                let start = 0xFFFF & !(1 << hole);
+                assert_eq!(decomposed.is_winnable(start), winnable.contains(start as usize));
+                assert_eq!(plain.is_winnable(start), winnable.contains(start as usize));
+            }
+            assert!(decomposed.decomposed > 0 && decomposed.explored < plain.explored);
+
+            let mut solver = RegionSolver::new(rules, true);
+            for seed in (1..1 << 16).step_by(97) {
+                assert_eq!(solver.best_pegs(seed), tablebase.best_pegs(seed) as u32);
+            }
+        }
+    }
+}
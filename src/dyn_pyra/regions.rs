@@ -0,0 +1,105 @@
+// Decomposition of a position into independent regions. The reach of a group of pegs is the
+// closure of its cells by the jump lines (two cells of a line in the reach add the third one),
+// a superset of the cells its pegs can ever occupy. Two groups interact when their reaches meet
+// or when a jump could start from one reach over the other. Groups that never interact are
+// played independently, a position is the sum of its regions.
+
+use super::{jump_partners, jumps, CellIndex, PyramideRules, Seed};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regions {
+    // departure and middle cells of every jump, arrival cell
+    lines: Vec<(Seed, CellIndex)>,
+    // touch[cell] : cells forming a (departure, middle) pair with cell
+    touch: Vec<Seed>,
+}
+
+impl Regions {
+    pub fn new(rules: PyramideRules) -> Regions {
+        let lines = jumps(rules).into_iter()
+            .map(|(_, [dep, mil, arr])| ((1 << dep) | (1 << mil), arr))
+            .collect();
+        Regions { lines, touch: jump_partners(rules) }
+    }
+
+    pub fn reach(&self, pegs: Seed) -> Seed {
+        let mut reach = pegs;
+        loop {
+            let new_reach = self.lines.iter()
+                .filter(|(pair, _)| reach & pair == *pair)
+                .fold(reach, |acc, (_, arr)| acc | (1 << arr));
+            if new_reach == reach {
+                return reach
+            }
+            reach = new_reach;
+        }
+    }
+
+    fn interact(&self, reach: Seed, other: Seed) -> bool {
+        if reach & other != 0 {
+            return true
+        }
+        (0..self.touch.len()).any(|cell| reach & (1 << cell) != 0 && self.touch[cell] & other != 0)
+    }
+
+    pub fn split(&self, seed: Seed) -> Vec<Seed> {
+        // pegs of each region, a single region when nothing can be separated
+        let mut groups: Vec<(Seed, Seed)> = (0..self.touch.len())
+            .filter(|cell| seed & (1 << cell) != 0)
+            .map(|cell| (1 << cell, 1 << cell))
+            .collect();
+        let mut merged = true;
+        while merged {
+            merged = false;
+            'search: for i in 0..groups.len() {
+                for j in i + 1..groups.len() {
+                    if self.interact(groups[i].1, groups[j].1) {
+                        let pegs = groups[i].0 | groups.swap_remove(j).0;
+                        groups[i] = (pegs, self.reach(pegs));
+                        merged = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+        let mut regions: Vec<Seed> = groups.into_iter().map(|(pegs, _)| pegs).collect();
+        regions.sort_unstable();
+        return regions
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::state_graph::{StateGraph, StateIndex};
+
+    #[test]
+    fn test_split() {
+        let regions = Regions::new(PyramideRules { lines: 4, diag_allowed: false });
+        // a4 and d1 d7 : far apart
+        assert_eq!(regions.split(0b1 | (1 << 9) | (1 << 15)), vec![0b1, 1 << 9, 1 << 15]);
+        // c4 b4 : c4 can jump over b4
+        assert_eq!(regions.split((1 << 6) | (1 << 2)), vec![(1 << 2) | (1 << 6)]);
+        assert_eq!(regions.split(0xFFFF), vec![0xFFFF]);
+        assert_eq!(regions.split(0), Vec::<Seed>::new());
+    }
+
+    #[test]
+    fn test_regions_are_independent() {
+        // every move of a position stays inside one of its regions
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let regions = Regions::new(rules);
+        let graph = StateGraph::build_full(rules);
+        for idx in 0..graph.node_count() as StateIndex {
+            let seed = graph.seed(idx);
+            let split = regions.split(seed);
+            assert_eq!(split.iter().fold(0, |acc, region| acc | region), seed);
+            for target in graph.neighbors_slice(idx) {
+                let changed = seed ^ graph.seed(*target);
+                let region = split.iter().find(|region| changed & **region != 0).expect("a region moved");
+                assert_eq!(changed & !regions.reach(*region), 0);
+            }
+        }
+    }
+}
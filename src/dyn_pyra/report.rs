@@ -0,0 +1,179 @@
+// Solvability report : for every board size and rule variant, the outcome of each starting hole
+// (solvable, fewest pegs that can be left, number of winning games, cells where the last peg
+// can end), written as Markdown grids shaped like the board (rows a.., columns 1..2*lines-1),
+// one grid per metric, or as CSV rows, one per hole.
+// The outcomes come from the tablebase and the winning paths count, boards up to 5 lines.
+
+use std::fmt::Write;
+
+use num_bigint::BigUint;
+
+use super::paths::WinningPaths;
+use super::state_graph::StateGraph;
+use super::tablebase::{Tablebase, MAX_TABLEBASE_LINES};
+use super::{CellIndex, Pyramide, PyramideRules, Seed};
+
+pub const MAX_REPORT_LINES: usize = MAX_TABLEBASE_LINES;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoleOutcome {
+    pub hole: CellIndex,
+    pub best_pegs: u8,
+    // winning games
+    pub solutions: BigUint,
+    pub final_cells: Vec<CellIndex>,
+}
+
+impl HoleOutcome {
+    pub fn solvable(&self) -> bool {
+        self.best_pegs == 1
+    }
+}
+
+pub fn hole_outcomes(rules: PyramideRules) -> Vec<HoleOutcome> {
+    assert!(rules.lines <= MAX_REPORT_LINES, "tablebase too large");
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let starts: Vec<Seed> = (0..cells_num).map(|hole| full_seed & !(1 << hole)).collect();
+    let tablebase = Tablebase::build(rules);
+    let graph = StateGraph::build_partial(starts.iter()
+        .map(|seed| Pyramide::init_from_seed(rules.lines, *seed, rules.diag_allowed))
+        .collect());
+    let paths = WinningPaths::count(&graph, &starts);
+    starts.iter().zip(paths.starts).enumerate()
+        .map(|(hole, (start, solutions))| HoleOutcome {
+            hole,
+            best_pegs: tablebase.best_pegs(*start),
+            solutions,
+            final_cells: tablebase.final_cells(*start),
+        })
+        .collect()
+}
+
+fn final_cells_names(pyra: &Pyramide, outcome: &HoleOutcome) -> String {
+    let names: Vec<String> = outcome.final_cells.iter().map(|cell| pyra.cell_name(*cell)).collect();
+    names.join(" ")
+}
+
+fn markdown_grid(pyra: &Pyramide, outcomes: &[HoleOutcome], title: &str, value: impl Fn(&HoleOutcome) -> String) -> String {
+    // the value of each starting hole at its place on the board, the cells off the board are empty
+    let columns = 2 * pyra.lines - 1;
+    let mut cells = vec![vec![String::new(); columns]; pyra.lines];
+    for outcome in outcomes {
+        let (row, col) = pyra.cell_position(outcome.hole);
+        cells[row][col] = value(outcome);
+    }
+    let mut grid = String::new();
+    writeln!(grid, "#### {}", title).unwrap();
+    writeln!(grid).unwrap();
+    let header: Vec<String> = (1..=columns).map(|col| col.to_string()).collect();
+    writeln!(grid, "|   | {} |", header.join(" | ")).unwrap();
+    writeln!(grid, "|---|{}", ":-:|".repeat(columns)).unwrap();
+    for (row, values) in cells.iter().enumerate() {
+        writeln!(grid, "| {} | {} |", (b'a' + row as u8) as char, values.join(" | ")).unwrap();
+    }
+    grid
+}
+
+pub fn markdown_table(rules: PyramideRules, outcomes: &[HoleOutcome]) -> String {
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    let solvable = outcomes.iter().filter(|outcome| outcome.solvable()).count();
+    let mut table = String::new();
+    writeln!(table, "### {} lines, {} diagonals : {}/{} starting holes solvable",
+        rules.lines, if rules.diag_allowed { "with" } else { "without" }, solvable, outcomes.len()).unwrap();
+    let grids = [
+        markdown_grid(&pyra, outcomes, "Solvable", |outcome| if outcome.solvable() { "yes" } else { "no" }.to_string()),
+        markdown_grid(&pyra, outcomes, "Best", |outcome| outcome.best_pegs.to_string()),
+        markdown_grid(&pyra, outcomes, "Solutions", |outcome| outcome.solutions.to_string()),
+        markdown_grid(&pyra, outcomes, "Final cells", |outcome|
+            if outcome.final_cells.is_empty() { "-".to_string() } else { final_cells_names(&pyra, outcome) }),
+    ];
+    for grid in grids {
+        writeln!(table).unwrap();
+        table.push_str(&grid);
+    }
+    table
+}
+
+pub fn csv_rows(rules: PyramideRules, outcomes: &[HoleOutcome]) -> String {
+    // without the header line, see CSV_HEADER
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    let mut rows = String::new();
+    for outcome in outcomes {
+        let (row, col) = pyra.cell_position(outcome.hole);
+        writeln!(rows, "{},{},{},{},{},{},{},{},{}",
+            rules.lines, rules.diag_allowed, pyra.cell_name(outcome.hole), (b'a' + row as u8) as char, col + 1,
+            outcome.solvable(), outcome.best_pegs, outcome.solutions, final_cells_names(&pyra, outcome)).unwrap();
+    }
+    rows
+}
+
+pub const CSV_HEADER: &str = "lines,diagonals,hole,row,column,solvable,best_pegs,solutions,final_cells";
+
+pub fn solvability_report(max_lines: usize, format: ReportFormat) -> String {
+    // every board from 3 lines to max_lines, without then with diagonals
+    let mut report = String::new();
+    match format {
+        ReportFormat::Markdown => writeln!(report, "## Starting hole solvability").unwrap(),
+        ReportFormat::Csv => writeln!(report, "{}", CSV_HEADER).unwrap(),
+    }
+    for lines in 3..=max_lines {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines, diag_allowed };
+            let outcomes = hole_outcomes(rules);
+            match format {
+                ReportFormat::Markdown => {
+                    writeln!(report).unwrap();
+                    report.push_str(&markdown_table(rules, &outcomes));
+                },
+                ReportFormat::Csv => report.push_str(&csv_rows(rules, &outcomes)),
+            }
+        }
+    }
+    report
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::retrograde::{solvable_starts, winnable_seeds};
+
+    #[test]
+    fn test_hole_outcomes() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let outcomes = hole_outcomes(rules);
+            let solvable = solvable_starts(rules, &winnable_seeds(rules));
+            for (outcome, (hole, solvable)) in outcomes.iter().zip(solvable) {
+                assert_eq!(outcome.hole, hole);
+                assert_eq!(outcome.solvable(), solvable);
+                assert_eq!(outcome.solutions != BigUint::ZERO, solvable);
+                assert_eq!(outcome.final_cells.is_empty(), !solvable);
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let markdown = solvability_report(3, ReportFormat::Markdown);
+        assert!(markdown.starts_with("## Starting hole solvability\n\n### 3 lines, without diagonals : "));
+        assert!(markdown.contains("#### Best\n\n|   | 1 | 2 | 3 | 4 | 5 |\n|---|:-:|:-:|:-:|:-:|:-:|\n| a |  |  | 5 |  |  |\n"));
+        assert!(markdown.contains("| c | 4 | 4 | 3 | 4 | 4 |"));
+        // one line per row in each of the four grids of the two boards
+        assert_eq!(markdown.lines().filter(|line| line.starts_with("| b |")).count(), 8);
+
+        let csv = solvability_report(3, ReportFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), 1 + 18);
+        assert!(lines[1].starts_with("3,false,a3,a,3,"));
+        assert!(lines.iter().all(|line| line.split(',').count() == 9));
+    }
+}
@@ -0,0 +1,112 @@
+// Backward search from the winning positions : starting from every single peg position,
+// un-jumps (Pyramide::coup_inverse) enumerate every position from which the game can be won.
+// One pass answers the solvability of every starting hole.
+
+use fixedbitset::FixedBitSet;
+use indicatif::{ProgressBar, ProgressIterator};
+
+use super::seed_array::SeedFlags;
+use super::{cherche_coups_inverses, coups_theoriques, CellIndex, Pyramide, PyramideRules, Seed};
+
+pub fn test_retrograde(rules: PyramideRules) {
+    let winnable = winnable_seeds(rules);
+    println!("Number of winnable positions : {:?}", winnable.count_ones(..));
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    for (hole, solvable) in solvable_starts(rules, &winnable) {
+        println!("Starting hole {} : {}", pyra.cell_name(hole), if solvable { "solvable" } else { "unsolvable" });
+    }
+}
+
+pub fn winnable_seeds(rules: PyramideRules) -> FixedBitSet {
+    // bit `seed` is set when a single peg can be reached from the position `seed`
+    let cells_num = rules.lines * rules.lines;
+    assert!(cells_num < usize::BITS as usize, "seed space too large for a bitset");
+    let mut winnable = FixedBitSet::with_capacity(1 << cells_num);
+    mark_winnable(rules, &mut winnable);
+    return winnable
+}
+
+pub fn mark_winnable(rules: PyramideRules, winnable: &mut impl SeedFlags) {
+    // same as winnable_seeds, into any flags array (SeedBits for a memory-mapped file)
+    let cells_num = rules.lines * rules.lines;
+    let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+
+    let mut layer: Vec<Seed> = (0..cells_num).map(|cell| 1 << cell).collect();
+    layer.iter().for_each(|seed| { winnable.put_seed(*seed); });
+    // each un-jump adds a peg, layers go from 1 peg to cells_num - 1 pegs
+    let prog_bar = ProgressBar::new(cells_num as u64 - 2);
+    for _ in (1..cells_num - 1).progress_with(prog_bar) {
+        let mut next_layer: Vec<Seed> = vec![];
+        for seed in layer.drain(..) {
+            let pyra = Pyramide::init_from_seed(rules.lines, seed, rules.diag_allowed);
+            for coup in cherche_coups_inverses(&pyra, &coups) {
+                let mut prev_pyra = pyra.clone();
+                prev_pyra.coup_inverse(&coup).expect("already checked");
+                let prev_seed = prev_pyra.seed();
+                if !winnable.put_seed(prev_seed) {
+                    next_layer.push(prev_seed);
+                }
+            }
+        }
+        layer = next_layer;
+    }
+}
+
+pub fn solvable_starts(rules: PyramideRules, winnable: &FixedBitSet) -> Vec<(CellIndex, bool)> {
+    let full_seed: Seed = (1 << (rules.lines * rules.lines)) - 1;
+    (0..rules.lines * rules.lines)
+        .map(|hole| (hole, winnable.contains((full_seed & !(1 << hole)) as usize)))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::seed_array::SeedBits;
+    use crate::dyn_pyra::state_graph::StateGraph;
+
+    #[test]
+    fn test_winnable_matches_forward_search() {
+        for (lines, diag_allowed) in [(3, false), (3, true), (4, false), (4, true)] {
+            let rules = PyramideRules { lines, diag_allowed };
+            let winnable = winnable_seeds(rules);
+            let graph = StateGraph::build_full(rules);
+            // forward : a position is winnable if it has one peg or a move to a winnable position
+            let mut nodes: Vec<u32> = (0..graph.node_count() as u32).collect();
+            nodes.sort_by_key(|idx| graph.seed(*idx).count_ones());
+            let mut forward = FixedBitSet::with_capacity(1 << (lines * lines));
+            for idx in nodes {
+                let seed = graph.seed(idx);
+                if seed.count_ones() == 1
+                    || graph.neighbors_slice(idx).iter().any(|n| forward.contains(graph.seed(*n) as usize)) {
+                    forward.insert(seed as usize);
+                }
+            }
+            assert_eq!(winnable, forward);
+        }
+    }
+
+    #[test]
+    fn test_mark_winnable_in_seed_bits() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let winnable = winnable_seeds(rules);
+        let mut bits = SeedBits::in_memory(rules);
+        mark_winnable(rules, &mut bits);
+        assert_eq!(bits.count_ones(), winnable.count_ones(..));
+        assert!((0..1 << 16).all(|seed| bits.get(seed) == winnable.contains(seed as usize)));
+    }
+
+    #[test]
+    fn test_solvable_starts() {
+        // README : the 4 lines board is unsolvable without diagonals, solvable with
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let starts = solvable_starts(rules, &winnable_seeds(rules));
+        assert_eq!(starts.len(), 16);
+        assert!(starts.iter().all(|(_, solvable)| !solvable));
+
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let starts = solvable_starts(rules, &winnable_seeds(rules));
+        assert!(starts.iter().any(|(_, solvable)| *solvable));
+    }
+}
@@ -0,0 +1,332 @@
+// Dense arrays indexed by seed, one entry for each of the 2^(lines*lines) positions : a bit for
+// the win/loss flags (SeedBits), a byte or more for distances and counts (SeedArray).
+// They live in memory or in a memory-mapped file, the file is sparse until written and the
+// system pages it in and out, datasets larger than the RAM are handled from disk.
+// A file starts with a 16 bytes header : magic, lines, diagonals, bits per entry.
+// Used by the tablebase, the retrograde analysis and the file-backed full state graph build
+// (out-degree of every seed).
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::marker::PhantomData;
+use std::path::Path;
+
+use fixedbitset::FixedBitSet;
+use indicatif::{ProgressBar, ProgressIterator};
+use memmap2::MmapMut;
+
+use super::checkpoint::invalid_data;
+use super::retrograde::mark_winnable;
+use super::{cherche_coups_possibles, coups_theoriques, Pyramide, PyramideRules, Seed};
+
+const MAGIC: &[u8; 8] = b"SEEDARR1";
+const HEADER_LEN: usize = 16;
+
+pub fn test_seed_array(rules: PyramideRules, dir: &Path) -> io::Result<()> {
+    // winnable flags and number of legal moves of every seed, in files
+    fs::create_dir_all(dir)?;
+    let mut winnable = SeedBits::create(dir.join("winnable.bin"), rules)?;
+    mark_winnable(rules, &mut winnable);
+    winnable.flush()?;
+    println!("Number of winnable positions : {}", winnable.count_ones());
+
+    let mut moves: SeedArray<u8> = SeedArray::create(dir.join("moves.bin"), rules)?;
+    let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+    let prog_bar = ProgressBar::new(moves.len() as u64);
+    for seed in (0..moves.len() as Seed).progress_with(prog_bar) {
+        let pyra = Pyramide::init_from_seed(rules.lines, seed, rules.diag_allowed);
+        moves.set(seed, cherche_coups_possibles(&pyra, &coups).map_or(0, |coups| coups.len()) as u8);
+    }
+    moves.flush()?;
+    let dead_ends = (0..moves.len() as Seed).filter(|seed| moves.get(*seed) == 0).count();
+    println!("Number of positions without a move : {}", dead_ends);
+    Ok(())
+}
+
+pub trait Entry: Copy + Default {
+    const BYTES: usize;
+
+    fn read(bytes: &[u8]) -> Self;
+
+    fn write(self, bytes: &mut [u8]);
+}
+
+macro_rules! entry_impl {
+    ($($t:ty),*) => {$(
+        impl Entry for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            fn read(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes(bytes.try_into().expect("entry size"))
+            }
+
+            fn write(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+
+entry_impl!(u8, u16, u32, u64);
+
+// flags by seed, implemented by FixedBitSet (memory) and SeedBits (memory or disk)
+pub trait SeedFlags {
+    fn contains_seed(&self, seed: Seed) -> bool;
+
+    // sets the flag, returns its previous value
+    fn put_seed(&mut self, seed: Seed) -> bool;
+}
+
+impl SeedFlags for FixedBitSet {
+    fn contains_seed(&self, seed: Seed) -> bool {
+        self.contains(seed as usize)
+    }
+
+    fn put_seed(&mut self, seed: Seed) -> bool {
+        self.put(seed as usize)
+    }
+}
+
+fn seeds_num(rules: PyramideRules) -> usize {
+    let cells_num = rules.lines * rules.lines;
+    assert!(cells_num < usize::BITS as usize - 3, "seed space too large for an array");
+    1 << cells_num
+}
+
+enum Storage {
+    Memory(Vec<u8>),
+    Mapped(MmapMut),
+}
+
+impl Storage {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Storage::Memory(bytes) => bytes,
+            Storage::Mapped(map) => &map[HEADER_LEN..],
+        }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        match self {
+            Storage::Memory(bytes) => bytes,
+            Storage::Mapped(map) => &mut map[HEADER_LEN..],
+        }
+    }
+
+    fn header(rules: PyramideRules, bits: usize) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..8].copy_from_slice(MAGIC);
+        header[8] = rules.lines as u8;
+        header[9] = rules.diag_allowed as u8;
+        header[10] = bits as u8;
+        header
+    }
+
+    fn create(path: &Path, rules: PyramideRules, bits: usize, len: usize) -> io::Result<Storage> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len((HEADER_LEN + len) as u64)?;
+        let mut storage = Storage::map(&file)?;
+        if let Storage::Mapped(map) = &mut storage {
+            map[..HEADER_LEN].copy_from_slice(&Storage::header(rules, bits));
+        }
+        Ok(storage)
+    }
+
+    fn open(path: &Path, rules: PyramideRules, bits: usize, len: usize) -> io::Result<Storage> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        if file.metadata()?.len() != (HEADER_LEN + len) as u64 {
+            return Err(invalid_data("seed array file of the wrong size"))
+        }
+        let storage = Storage::map(&file)?;
+        if let Storage::Mapped(map) = &storage
+            && map[..HEADER_LEN] != Storage::header(rules, bits) {
+            return Err(invalid_data("seed array file for other rules or entries"))
+        }
+        Ok(storage)
+    }
+
+    fn map(file: &File) -> io::Result<Storage> {
+        // SAFETY: the file is owned by the array for its lifetime, it must not be modified
+        // by another process meanwhile
+        let map = unsafe { MmapMut::map_mut(file)? };
+        Ok(Storage::Mapped(map))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self {
+            Storage::Memory(_) => Ok(()),
+            Storage::Mapped(map) => map.flush(),
+        }
+    }
+}
+
+pub struct SeedArray<T: Entry> {
+    rules: PyramideRules,
+    storage: Storage,
+    entry: PhantomData<T>,
+}
+
+impl<T: Entry> SeedArray<T> {
+    fn with_storage(rules: PyramideRules, storage: Storage) -> SeedArray<T> {
+        SeedArray { rules, storage, entry: PhantomData }
+    }
+
+    pub fn in_memory(rules: PyramideRules) -> SeedArray<T> {
+        let storage = Storage::Memory(vec![0; seeds_num(rules) * T::BYTES]);
+        SeedArray::with_storage(rules, storage)
+    }
+
+    pub fn create(path: impl AsRef<Path>, rules: PyramideRules) -> io::Result<SeedArray<T>> {
+        // new file, every entry is 0, an existing file is truncated
+        let storage = Storage::create(path.as_ref(), rules, T::BYTES * 8, seeds_num(rules) * T::BYTES)?;
+        Ok(SeedArray::with_storage(rules, storage))
+    }
+
+    pub fn open(path: impl AsRef<Path>, rules: PyramideRules) -> io::Result<SeedArray<T>> {
+        let storage = Storage::open(path.as_ref(), rules, T::BYTES * 8, seeds_num(rules) * T::BYTES)?;
+        Ok(SeedArray::with_storage(rules, storage))
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.storage.bytes().len() / T::BYTES
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, seed: Seed) -> T {
+        let start = seed as usize * T::BYTES;
+        T::read(&self.storage.bytes()[start..start + T::BYTES])
+    }
+
+    pub fn set(&mut self, seed: Seed, value: T) {
+        let start = seed as usize * T::BYTES;
+        value.write(&mut self.storage.bytes_mut()[start..start + T::BYTES]);
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.storage.flush()
+    }
+}
+
+pub struct SeedBits {
+    rules: PyramideRules,
+    storage: Storage,
+}
+
+impl SeedBits {
+    pub fn in_memory(rules: PyramideRules) -> SeedBits {
+        SeedBits { rules, storage: Storage::Memory(vec![0; seeds_num(rules).div_ceil(8)]) }
+    }
+
+    pub fn create(path: impl AsRef<Path>, rules: PyramideRules) -> io::Result<SeedBits> {
+        // new file, every flag is cleared, an existing file is truncated
+        let storage = Storage::create(path.as_ref(), rules, 1, seeds_num(rules).div_ceil(8))?;
+        Ok(SeedBits { rules, storage })
+    }
+
+    pub fn open(path: impl AsRef<Path>, rules: PyramideRules) -> io::Result<SeedBits> {
+        let storage = Storage::open(path.as_ref(), rules, 1, seeds_num(rules).div_ceil(8))?;
+        Ok(SeedBits { rules, storage })
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        seeds_num(self.rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, seed: Seed) -> bool {
+        self.storage.bytes()[seed as usize / 8] & (1 << (seed % 8)) != 0
+    }
+
+    pub fn set(&mut self, seed: Seed, value: bool) {
+        let byte = &mut self.storage.bytes_mut()[seed as usize / 8];
+        if value {
+            *byte |= 1 << (seed % 8);
+        } else {
+            *byte &= !(1 << (seed % 8));
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.storage.bytes().iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.storage.flush()
+    }
+}
+
+impl SeedFlags for SeedBits {
+    fn contains_seed(&self, seed: Seed) -> bool {
+        self.get(seed)
+    }
+
+    fn put_seed(&mut self, seed: Seed) -> bool {
+        let previous = self.get(seed);
+        self.set(seed, true);
+        previous
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::checkpoint::test_dir;
+
+    #[test]
+    fn test_in_memory() {
+        let rules = PyramideRules { lines: 3, diag_allowed: false };
+        let mut array: SeedArray<u16> = SeedArray::in_memory(rules);
+        assert_eq!(array.len(), 512);
+        array.set(511, 300);
+        array.set(3, 7);
+        assert_eq!((array.get(511), array.get(3), array.get(4)), (300, 7, 0));
+
+        let mut bits = SeedBits::in_memory(rules);
+        assert!(!bits.put_seed(9));
+        assert!(bits.put_seed(9));
+        bits.set(511, true);
+        bits.set(9, false);
+        assert_eq!((bits.get(9), bits.get(511), bits.count_ones()), (false, true, 1));
+    }
+
+    #[test]
+    fn test_mapped_file() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let dir = test_dir("seed_array");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("distances.bin");
+        {
+            let mut array: SeedArray<u32> = SeedArray::create(&path, rules).unwrap();
+            array.set(0xFFFE, 123_456);
+            array.flush().unwrap();
+        }
+        let array: SeedArray<u32> = SeedArray::open(&path, rules).unwrap();
+        assert_eq!((array.get(0xFFFE), array.get(0xFFFD), array.len()), (123_456, 0, 1 << 16));
+        // other entries or rules
+        assert!(SeedArray::<u8>::open(&path, rules).is_err());
+        assert!(SeedArray::<u32>::open(&path, PyramideRules { lines: 4, diag_allowed: false }).is_err());
+
+        let path = dir.join("flags.bin");
+        let mut bits = SeedBits::create(&path, rules).unwrap();
+        bits.set(12345, true);
+        drop(bits);
+        let bits = SeedBits::open(&path, rules).unwrap();
+        assert!(bits.get(12345) && bits.count_ones() == 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
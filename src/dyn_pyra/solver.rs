@@ -0,0 +1,130 @@
+// Depth first search of a winning sequence of moves from a position.
+// Positions already proven lost are remembered, an optional pagoda pruning cuts the
+// positions from which no single peg finish is possible.
+
+use std::collections::HashSet;
+
+use super::pagoda::PagodaPruning;
+use super::{coups_theoriques, Coup, Pyramide, Seed};
+
+pub struct Solver {
+    coups: Vec<Coup>,
+    pruning: Option<PagodaPruning>,
+    lost: HashSet<Seed>,
+    pub explored: usize,
+    pub pruned: usize,
+}
+
+impl Solver {
+    pub fn new(pyra: &Pyramide, pruning: Option<PagodaPruning>) -> Solver {
+        Solver { coups: coups_theoriques(pyra), pruning, lost: HashSet::new(), explored: 0, pruned: 0 }
+    }
+
+    pub fn solve(&mut self, pyra: &Pyramide) -> Option<Vec<Coup>> {
+        let mut solution = vec![];
+        if self.search(pyra, &mut solution) {
+            return Some(solution)
+        }
+        return None
+    }
+
+    fn search(&mut self, pyra: &Pyramide, solution: &mut Vec<Coup>) -> bool {
+        if pyra.partie_gagne() {
+            return true
+        }
+        let seed = pyra.seed();
+        if self.lost.contains(&seed) {
+            return false
+        }
+        self.explored += 1;
+        if self.pruning.as_ref().is_some_and(|pruning| pruning.is_lost(seed)) {
+            self.pruned += 1;
+            self.lost.insert(seed);
+            return false
+        }
+        for idx in 0..self.coups.len() {
+            let coup = self.coups[idx];
+            if !pyra.is_coup_valid(&coup) {
+                continue;
+            }
+            let mut new_pyra = pyra.clone();
+            new_pyra.coup(&coup).expect("already checked");
+            solution.push(coup);
+            if self.search(&new_pyra, solution) {
+                return true
+            }
+            solution.pop();
+        }
+        self.lost.insert(seed);
+        return false
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::pagoda::certify_unsolvable;
+    use crate::dyn_pyra::retrograde::winnable_seeds;
+    use crate::dyn_pyra::PyramideRules;
+
+    #[test]
+    fn test_solve_matches_retrograde() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let winnable = winnable_seeds(rules);
+            for hole in 0..16 {
+                let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), diag_allowed);
+                let solution = Solver::new(&start, None).solve(&start);
+                assert_eq!(solution.is_some(), winnable.contains(start.seed() as usize));
+                if let Some(solution) = solution {
+                    assert_eq!(solution.len(), 14);
+                    let mut pyra = start.clone();
+                    for coup in solution {
+                        pyra.coup(&coup).expect("valid solution");
+                    }
+                    assert!(pyra.partie_gagne());
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_pruned_solve_matches_retrograde() {
+        for diag_allowed in [false, true] {
+            let rules = PyramideRules { lines: 4, diag_allowed };
+            let winnable = winnable_seeds(rules);
+            for hole in 0..16 {
+                let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), diag_allowed);
+                let mut solver = Solver::new(&start, Some(PagodaPruning::new(rules, start.seed())));
+                let solution = solver.solve(&start);
+                assert_eq!(solution.is_some(), winnable.contains(start.seed() as usize));
+                if let Some(solution) = solution {
+                    let mut pyra = start.clone();
+                    for coup in solution {
+                        pyra.coup(&coup).expect("valid solution");
+                    }
+                    assert!(pyra.partie_gagne());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_readme_certificates() {
+        // without diagonals, certificates for 12 of the 16 starting holes, the pruned search
+        // proves the 4 others lost
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let pyra = Pyramide::new(4, false);
+        let mut uncertified = vec![];
+        for hole in 0..16 {
+            let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), false);
+            if certify_unsolvable(rules, start.seed()).is_none() {
+                uncertified.push(pyra.cell_name(hole));
+                let mut solver = Solver::new(&start, Some(PagodaPruning::new(rules, start.seed())));
+                assert!(solver.solve(&start).is_none());
+                assert!(solver.pruned > 0);
+            }
+        }
+        assert_eq!(uncertified, vec!["b3", "b5", "c2", "c6"]);
+    }
+}
@@ -0,0 +1,788 @@
+// Compact storage for the state graph: nodes are dense indices into a seed table and the
+// adjacency is kept in compressed sparse row form (offsets + targets), edges being labelled
+// by the index of the move in `coups_theoriques`.
+// Compared to GraphMap<Seed, Coup, Undirected> this avoids hashing u128 keys and storing a
+// full Coup per edge, which is what makes the 5 lines full graph fit in memory.
+// Edges follow the direction of play (a jump always removes a peg).
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::iter::Zip;
+use std::ops::Range;
+use std::path::Path;
+use std::slice::Iter as SliceIter;
+
+use fixedbitset::FixedBitSet;
+use indicatif::ProgressBar;
+use petgraph::visit::{Data, EdgeCount, EdgeRef, GraphBase, GraphProp, IntoEdgeReferences, IntoEdges,
+    IntoNeighbors, IntoNodeIdentifiers, NodeCompactIndexable, NodeCount, NodeIndexable, Visitable};
+use petgraph::Directed;
+
+use super::checkpoint::{invalid_data, Checkpoint, Decoder, Encoder};
+use super::seed_array::SeedArray;
+use super::{coups_theoriques, Coup, Pyramide, PyramideRules, Seed};
+
+pub type StateIndex = u32;
+pub type CoupIndex = u16;
+
+const FULL_GRAPH: &str = "full_graph";
+const PARTIAL_GRAPH: &str = "partial_graph";
+const EXPLORING: u8 = 0;
+const FILLING: u8 = 1;
+// number of nodes handled between two checks of the checkpoint timer
+const SAVE_CHECK_EVERY: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeedIndex {
+    // seeds start..start+len, index is seed - start
+    Range { start: Seed, len: usize },
+    // sorted seeds, index found by binary search
+    Sorted(Vec<Seed>),
+}
+
+impl SeedIndex {
+    pub fn len(&self) -> usize {
+        match self {
+            SeedIndex::Range { len, .. } => *len,
+            SeedIndex::Sorted(seeds) => seeds.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn sorted(seeds: HashSet<Seed>) -> SeedIndex {
+        let mut seeds: Vec<Seed> = seeds.into_iter().collect();
+        seeds.sort_unstable();
+        SeedIndex::Sorted(seeds)
+    }
+
+    pub fn seed(&self, idx: StateIndex) -> Seed {
+        match self {
+            SeedIndex::Range { start, .. } => start + idx as Seed,
+            SeedIndex::Sorted(seeds) => seeds[idx as usize],
+        }
+    }
+
+    pub fn index(&self, seed: Seed) -> Option<StateIndex> {
+        let idx = match self {
+            SeedIndex::Range { start, len } => {
+                if seed < *start || seed - start >= *len as Seed {
+                    return None
+                }
+                (seed - start) as usize
+            },
+            SeedIndex::Sorted(seeds) => seeds.binary_search(&seed).ok()?,
+        };
+        Some(idx as StateIndex)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LayerStats {
+    pub pegs: usize,
+    pub nodes: usize,
+    // moves leaving the layer
+    pub edges: usize,
+    // positions without any move
+    pub dead_ends: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateGraph {
+    rules: PyramideRules,
+    seeds: SeedIndex,
+    coups: Vec<Coup>,
+    offsets: Vec<u32>,
+    targets: Vec<StateIndex>,
+    labels: Vec<CoupIndex>,
+}
+
+impl StateGraph {
+    fn with_seeds(rules: PyramideRules, seeds: SeedIndex) -> StateGraph {
+        assert!(seeds.len() <= StateIndex::MAX as usize, "too many states for a u32 index");
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        assert!(coups.len() <= CoupIndex::MAX as usize);
+        let mut offsets = Vec::with_capacity(seeds.len() + 1);
+        offsets.push(0);
+        StateGraph { rules, seeds, coups, offsets, targets: vec![], labels: vec![] }
+    }
+
+    fn full_seed_index(rules: PyramideRules) -> SeedIndex {
+        // Same seeds as build_full_graph : every position but the empty and the full board
+        let max_seed: Seed = 2_u128.pow((rules.lines*rules.lines).try_into().unwrap()) - 1;
+        SeedIndex::Range { start: 1, len: (max_seed - 1) as usize }
+    }
+
+    pub fn build_full(rules: PyramideRules) -> StateGraph {
+        let mut graph = StateGraph::with_seeds(rules, StateGraph::full_seed_index(rules));
+        graph.push_remaining_nodes(|_| Ok(())).expect("nothing to save");
+        return graph
+    }
+
+    pub fn build_full_in(dir: &Path, rules: PyramideRules) -> io::Result<StateGraph> {
+        // Full graph through dir/degrees.bin, a mapped seed array of the out-degree of every seed
+        // (degree + 1, 0 for a seed not handled yet). An interrupted build resumes from the
+        // degrees in the file, the edges are then allocated at their exact size.
+        fs::create_dir_all(dir)?;
+        let path = dir.join("degrees.bin");
+        let mut degrees: SeedArray<u16> = if path.exists() {
+            SeedArray::open(&path, rules)?
+        } else {
+            SeedArray::create(&path, rules)?
+        };
+        let mut graph = StateGraph::with_seeds(rules, StateGraph::full_seed_index(rules));
+        let prog_bar = ProgressBar::new(graph.seeds.len() as u64);
+        let mut edges = 0;
+        for idx in 0..graph.seeds.len() as StateIndex {
+            let seed = graph.seeds.seed(idx);
+            if degrees.get(seed) == 0 {
+                let pyra = Pyramide::init_from_seed(rules.lines, seed, rules.diag_allowed);
+                let degree = graph.coups.iter().filter(|coup| pyra.is_coup_valid(coup)).count();
+                degrees.set(seed, degree as u16 + 1);
+            }
+            edges += degrees.get(seed) as usize - 1;
+            prog_bar.inc(1);
+        }
+        prog_bar.finish();
+        degrees.flush()?;
+
+        graph.targets.reserve_exact(edges);
+        graph.labels.reserve_exact(edges);
+        graph.push_remaining_nodes(|_| Ok(()))?;
+        if graph.edge_count() != edges {
+            return Err(invalid_data("degrees file of another graph"))
+        }
+        Ok(graph)
+    }
+
+    pub fn build_full_resumable(checkpoint: &mut Checkpoint) -> io::Result<StateGraph> {
+        let rules = checkpoint.rules();
+        let mut graph = match checkpoint.load(FULL_GRAPH)? {
+            Some(data) => StateGraph::decode(rules, &mut Decoder::new(&data))?,
+            None => StateGraph::with_seeds(rules, StateGraph::full_seed_index(rules)),
+        };
+        graph.push_remaining_nodes(|graph| {
+            if !checkpoint.is_due() {
+                return Ok(())
+            }
+            let mut enc = Encoder::new();
+            graph.encode(&mut enc);
+            checkpoint.save(FULL_GRAPH, &enc.finish())
+        })?;
+        checkpoint.clear(FULL_GRAPH)?;
+        Ok(graph)
+    }
+
+    pub fn build_partial(to_visit: Vec<Pyramide>) -> StateGraph {
+        // Create graph with all reachable positions from the positions in to_visit
+        StateGraph::try_build_partial(to_visit).expect("too many states for a u32 index")
+    }
+
+    pub fn try_build_partial(to_visit: Vec<Pyramide>) -> Option<StateGraph> {
+        // None when the reachable positions do not fit a StateIndex
+        let rules = PyramideRules { lines: to_visit[0].lines, diag_allowed: to_visit[0].diag_allowed };
+        let mut visited: HashSet<Seed> = to_visit.iter().map(|p| p.seed()).collect();
+        let mut to_visit: Vec<Seed> = visited.iter().copied().collect();
+        explore(rules, &mut visited, &mut to_visit, |_, _| Ok(())).expect("nothing to save");
+        if visited.len() > StateIndex::MAX as usize {
+            return None
+        }
+
+        let mut graph = StateGraph::with_seeds(rules, SeedIndex::sorted(visited));
+        graph.push_remaining_nodes(|_| Ok(())).expect("nothing to save");
+        return Some(graph)
+    }
+
+    pub fn build_partial_resumable(to_visit: Vec<Pyramide>, checkpoint: &mut Checkpoint) -> io::Result<StateGraph> {
+        // The checkpoint holds the start positions, then either the exploration state
+        // (visited seeds and seeds left to visit) or the graph being filled
+        let rules = checkpoint.rules();
+        let mut starts: Vec<Seed> = to_visit.iter().map(|p| p.seed()).collect();
+        starts.sort_unstable();
+        starts.dedup();
+        let mut header = Encoder::new();
+        header.seeds(&starts);
+        let header = header.finish();
+
+        let saved = checkpoint.load(PARTIAL_GRAPH)?.filter(|data| data.starts_with(&header));
+        let mut graph = match saved {
+            Some(data) => {
+                let mut dec = Decoder::new(&data[header.len()..]);
+                match dec.u8()? {
+                    EXPLORING => {
+                        let mut visited: HashSet<Seed> = dec.seeds()?.into_iter().collect();
+                        let mut to_visit = dec.seeds()?;
+                        explore_with_checkpoint(rules, &mut visited, &mut to_visit, checkpoint, &header)?;
+                        StateGraph::with_seeds(rules, SeedIndex::sorted(visited))
+                    },
+                    FILLING => StateGraph::decode(rules, &mut dec)?,
+                    _ => return Err(invalid_data("unknown partial graph phase")),
+                }
+            },
+            None => {
+                let mut visited: HashSet<Seed> = starts.iter().copied().collect();
+                let mut to_visit = starts.clone();
+                explore_with_checkpoint(rules, &mut visited, &mut to_visit, checkpoint, &header)?;
+                StateGraph::with_seeds(rules, SeedIndex::sorted(visited))
+            },
+        };
+        graph.push_remaining_nodes(|graph| {
+            if !checkpoint.is_due() {
+                return Ok(())
+            }
+            let mut enc = Encoder::new();
+            enc.bytes(&header);
+            enc.u8(FILLING);
+            graph.encode(&mut enc);
+            checkpoint.save(PARTIAL_GRAPH, &enc.finish())
+        })?;
+        checkpoint.clear(PARTIAL_GRAPH)?;
+        Ok(graph)
+    }
+
+    fn push_remaining_nodes(&mut self, mut save: impl FnMut(&StateGraph) -> io::Result<()>) -> io::Result<()> {
+        let done = self.offsets.len() - 1;
+        let prog_bar = ProgressBar::new(self.seeds.len() as u64);
+        prog_bar.set_position(done as u64);
+        for idx in done..self.seeds.len() {
+            self.push_node(self.seeds.seed(idx as StateIndex));
+            prog_bar.inc(1);
+            if idx.is_multiple_of(SAVE_CHECK_EVERY) {
+                save(self)?;
+            }
+        }
+        prog_bar.finish();
+        Ok(())
+    }
+
+    fn encode(&self, enc: &mut Encoder) {
+        match &self.seeds {
+            SeedIndex::Range { start, len } => {
+                enc.u8(0);
+                enc.seed(*start);
+                enc.u64(*len as u64);
+            },
+            SeedIndex::Sorted(seeds) => {
+                enc.u8(1);
+                enc.seeds(seeds);
+            },
+        }
+        enc.u32s(&self.offsets);
+        enc.u32s(&self.targets);
+        enc.u16s(&self.labels);
+    }
+
+    fn decode(rules: PyramideRules, dec: &mut Decoder) -> io::Result<StateGraph> {
+        let seeds = match dec.u8()? {
+            0 => SeedIndex::Range { start: dec.seed()?, len: dec.u64()? as usize },
+            1 => SeedIndex::Sorted(dec.seeds()?),
+            _ => return Err(invalid_data("unknown seed index")),
+        };
+        let mut graph = StateGraph::with_seeds(rules, seeds);
+        graph.offsets = dec.u32s()?;
+        graph.targets = dec.u32s()?;
+        graph.labels = dec.u16s()?;
+        let consistent = !graph.offsets.is_empty()
+            && graph.offsets.len() <= graph.seeds.len() + 1
+            && graph.offsets.last() == Some(&(graph.targets.len() as u32))
+            && graph.labels.len() == graph.targets.len();
+        if !consistent {
+            return Err(invalid_data("inconsistent state graph"))
+        }
+        Ok(graph)
+    }
+
+    fn push_node(&mut self, seed: Seed) {
+        // nodes must be pushed in index order
+        debug_assert_eq!(self.seeds.index(seed), Some((self.offsets.len() - 1) as StateIndex));
+        let pyra = Pyramide::init_from_seed(self.rules.lines, seed, self.rules.diag_allowed);
+        for (label, coup) in self.coups.iter().enumerate() {
+            if !pyra.is_coup_valid(coup) {
+                continue;
+            }
+            let mut new_pyra = pyra.clone();
+            new_pyra.coup(coup).expect("already checked");
+            let target = self.seeds.index(new_pyra.seed()).expect("target is part of the graph");
+            self.targets.push(target);
+            self.labels.push(label as CoupIndex);
+        }
+        self.offsets.push(self.targets.len().try_into().expect("too many edges for a u32 offset"));
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.rules
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.seeds.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn layer_sizes(&self) -> Vec<usize> {
+        // number of positions for each number of pegs
+        let mut sizes = vec![0; self.rules.lines * self.rules.lines + 1];
+        for idx in 0..self.node_count() as StateIndex {
+            sizes[self.seed(idx).count_ones() as usize] += 1;
+        }
+        sizes
+    }
+
+    pub fn layer_stats(&self) -> Vec<LayerStats> {
+        // one entry per number of pegs, from 0 to lines*lines
+        let mut stats: Vec<LayerStats> = (0..=self.rules.lines * self.rules.lines)
+            .map(|pegs| LayerStats { pegs, ..LayerStats::default() })
+            .collect();
+        for idx in 0..self.node_count() as StateIndex {
+            let layer = &mut stats[self.seed(idx).count_ones() as usize];
+            let out_degree = self.neighbors_slice(idx).len();
+            layer.nodes += 1;
+            layer.edges += out_degree;
+            layer.dead_ends += (out_degree == 0) as usize;
+        }
+        stats
+    }
+
+    pub fn seed(&self, idx: StateIndex) -> Seed {
+        self.seeds.seed(idx)
+    }
+
+    pub fn index(&self, seed: Seed) -> Option<StateIndex> {
+        self.seeds.index(seed)
+    }
+
+    pub fn pyramide(&self, idx: StateIndex) -> Pyramide {
+        Pyramide::init_from_seed(self.rules.lines, self.seed(idx), self.rules.diag_allowed)
+    }
+
+    pub fn coup(&self, label: CoupIndex) -> Coup {
+        self.coups[label as usize]
+    }
+
+    fn edge_range(&self, idx: StateIndex) -> Range<usize> {
+        self.offsets[idx as usize] as usize..self.offsets[idx as usize + 1] as usize
+    }
+
+    pub fn neighbors_slice(&self, idx: StateIndex) -> &[StateIndex] {
+        &self.targets[self.edge_range(idx)]
+    }
+
+    pub fn labels_slice(&self, idx: StateIndex) -> &[CoupIndex] {
+        &self.labels[self.edge_range(idx)]
+    }
+
+    pub fn coups_slice(&self) -> &[Coup] {
+        // every label, self.coup(label) == self.coups_slice()[label]
+        &self.coups
+    }
+
+    pub fn node_seeds(&self) -> Vec<Seed> {
+        // seed of every node, in index order
+        (0..self.node_count() as StateIndex).map(|idx| self.seed(idx)).collect()
+    }
+
+    pub fn edge_pairs(&self) -> Vec<[StateIndex; 2]> {
+        // (source, target) of every edge, by source then in the order of edge_labels()
+        (0..self.node_count() as StateIndex)
+            .flat_map(|idx| self.neighbors_slice(idx).iter().map(move |target| [idx, *target]))
+            .collect()
+    }
+
+    pub fn edge_labels(&self) -> &[CoupIndex] {
+        &self.labels
+    }
+}
+
+fn explore(
+    rules: PyramideRules,
+    visited: &mut HashSet<Seed>,
+    to_visit: &mut Vec<Seed>,
+    mut save: impl FnMut(&HashSet<Seed>, &Vec<Seed>) -> io::Result<()>
+) -> io::Result<()> {
+    let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+    let mut explored: usize = 0;
+    while let Some(seed) = to_visit.pop() {
+        let pyra = Pyramide::init_from_seed(rules.lines, seed, rules.diag_allowed);
+        for coup in coups.iter().filter(|c| pyra.is_coup_valid(c)) {
+            let mut new_pyra = pyra.clone();
+            new_pyra.coup(coup).expect("already checked");
+            let new_seed = new_pyra.seed();
+            if visited.insert(new_seed) {
+                to_visit.push(new_seed);
+            }
+        }
+        explored += 1;
+        if explored.is_multiple_of(SAVE_CHECK_EVERY) {
+            save(visited, to_visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn explore_with_checkpoint(
+    rules: PyramideRules,
+    visited: &mut HashSet<Seed>,
+    to_visit: &mut Vec<Seed>,
+    checkpoint: &mut Checkpoint,
+    header: &[u8]
+) -> io::Result<()> {
+    explore(rules, visited, to_visit, |visited, to_visit| {
+        if !checkpoint.is_due() {
+            return Ok(())
+        }
+        let mut enc = Encoder::new();
+        enc.bytes(header);
+        enc.u8(EXPLORING);
+        enc.seeds(&visited.iter().copied().collect::<Vec<Seed>>());
+        enc.seeds(to_visit);
+        checkpoint.save(PARTIAL_GRAPH, &enc.finish())
+    })
+}
+
+// petgraph adapters, so that connected_components, dijkstra, Dfs, ... work on StateGraph
+
+#[derive(Debug, Clone, Copy)]
+pub struct StateEdge<'a> {
+    source: StateIndex,
+    target: StateIndex,
+    label: &'a CoupIndex,
+    id: usize,
+}
+
+impl EdgeRef for StateEdge<'_> {
+    type NodeId = StateIndex;
+    type EdgeId = usize;
+    type Weight = CoupIndex;
+
+    fn source(&self) -> StateIndex {
+        self.source
+    }
+
+    fn target(&self) -> StateIndex {
+        self.target
+    }
+
+    fn weight(&self) -> &CoupIndex {
+        self.label
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl GraphBase for StateGraph {
+    type NodeId = StateIndex;
+    type EdgeId = usize;
+}
+
+impl Data for StateGraph {
+    type NodeWeight = ();
+    type EdgeWeight = CoupIndex;
+}
+
+impl GraphProp for StateGraph {
+    type EdgeType = Directed;
+}
+
+impl NodeCount for StateGraph {
+    fn node_count(&self) -> usize {
+        self.seeds.len()
+    }
+}
+
+impl EdgeCount for StateGraph {
+    fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+}
+
+impl NodeIndexable for StateGraph {
+    fn node_bound(&self) -> usize {
+        self.seeds.len()
+    }
+
+    fn to_index(&self, a: StateIndex) -> usize {
+        a as usize
+    }
+
+    fn from_index(&self, i: usize) -> StateIndex {
+        i as StateIndex
+    }
+}
+
+impl NodeCompactIndexable for StateGraph {}
+
+impl Visitable for StateGraph {
+    type Map = FixedBitSet;
+
+    fn visit_map(&self) -> FixedBitSet {
+        FixedBitSet::with_capacity(self.seeds.len())
+    }
+
+    fn reset_map(&self, map: &mut FixedBitSet) {
+        map.clear();
+        map.grow(self.seeds.len());
+    }
+}
+
+impl<'a> IntoNeighbors for &'a StateGraph {
+    type Neighbors = std::iter::Copied<SliceIter<'a, StateIndex>>;
+
+    fn neighbors(self, a: StateIndex) -> Self::Neighbors {
+        self.neighbors_slice(a).iter().copied()
+    }
+}
+
+impl IntoNodeIdentifiers for &StateGraph {
+    type NodeIdentifiers = Range<StateIndex>;
+
+    fn node_identifiers(self) -> Self::NodeIdentifiers {
+        0..self.seeds.len() as StateIndex
+    }
+}
+
+pub struct Edges<'a> {
+    source: StateIndex,
+    id: usize,
+    iter: Zip<SliceIter<'a, StateIndex>, SliceIter<'a, CoupIndex>>,
+}
+
+impl<'a> Iterator for Edges<'a> {
+    type Item = StateEdge<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (target, label) = self.iter.next()?;
+        let edge = StateEdge { source: self.source, target: *target, label, id: self.id };
+        self.id += 1;
+        Some(edge)
+    }
+}
+
+impl<'a> IntoEdges for &'a StateGraph {
+    type Edges = Edges<'a>;
+
+    fn edges(self, a: StateIndex) -> Edges<'a> {
+        let range = self.edge_range(a);
+        Edges {
+            source: a,
+            id: range.start,
+            iter: self.targets[range.clone()].iter().zip(self.labels[range].iter()),
+        }
+    }
+}
+
+pub struct EdgeReferences<'a> {
+    graph: &'a StateGraph,
+    next_source: StateIndex,
+    edges: Option<Edges<'a>>,
+}
+
+impl<'a> Iterator for EdgeReferences<'a> {
+    type Item = StateEdge<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(edge) = self.edges.as_mut().and_then(|edges| edges.next()) {
+                return Some(edge)
+            }
+            if self.next_source as usize >= self.graph.node_count() {
+                return None
+            }
+            self.edges = Some(self.graph.edges(self.next_source));
+            self.next_source += 1;
+        }
+    }
+}
+
+impl<'a> IntoEdgeReferences for &'a StateGraph {
+    type EdgeRef = StateEdge<'a>;
+    type EdgeReferences = EdgeReferences<'a>;
+
+    fn edge_references(self) -> EdgeReferences<'a> {
+        EdgeReferences { graph: self, next_source: 0, edges: None }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::{connected_components, dijkstra};
+    use petgraph::visit::Dfs;
+
+    use std::time::Duration;
+
+    use super::*;
+    use crate::dyn_pyra::checkpoint::test_dir;
+    use crate::dyn_pyra::{build_full_graph, build_partial_graph};
+
+    #[test]
+    fn test_full_graph_matches_graphmap() {
+        for (lines, diag_allowed) in [(3, false), (3, true), (4, false), (4, true)] {
+            let graph = StateGraph::build_full(PyramideRules { lines, diag_allowed });
+            let graph_map = build_full_graph(lines, diag_allowed);
+            assert_eq!(graph.node_count(), graph_map.node_count());
+            assert_eq!(graph.edge_count(), graph_map.edge_count());
+            assert_eq!(connected_components(&graph), connected_components(&graph_map));
+            assert_eq!(graph.layer_sizes().iter().sum::<usize>(), graph.node_count());
+            let stats = graph.layer_stats();
+            assert_eq!(stats.iter().map(|layer| layer.nodes).collect::<Vec<usize>>(), graph.layer_sizes());
+            assert_eq!(stats.iter().map(|layer| layer.edges).sum::<usize>(), graph.edge_count());
+            assert_eq!(stats[1].dead_ends, stats[1].nodes);
+        }
+    }
+
+    #[test]
+    fn test_partial_graph_matches_graphmap() {
+        let start = vec![Pyramide::init_from_seed(4, 65534, true)];
+        let graph = StateGraph::build_partial(start.clone());
+        let graph_map = build_partial_graph(start);
+        assert_eq!(graph.node_count(), graph_map.node_count());
+        assert_eq!(graph.edge_count(), graph_map.edge_count());
+        assert_eq!(connected_components(&graph), 1);
+        for idx in 0..graph.node_count() as StateIndex {
+            assert!(graph_map.contains_node(graph.seed(idx)));
+            for (target, label) in graph.neighbors_slice(idx).iter().zip(graph.labels_slice(idx)) {
+                let coup = graph_map.edge_weight(graph.seed(idx), graph.seed(*target)).expect("same edges");
+                assert_eq!(graph.coup(*label), *coup);
+            }
+        }
+    }
+
+    #[test]
+    fn test_edges_and_traversals() {
+        // _ _ F
+        // _ T T F
+        // F T T T F
+        // seed : 230
+        let graph = StateGraph::build_partial(vec![Pyramide::init_from_seed(3, 230, false)]);
+        let start = graph.index(230).expect("start is a node");
+        assert_eq!(graph.edges(start).count(), 4);
+        assert_eq!(graph.edge_references().count(), graph.edge_count());
+        for (id, edge) in graph.edge_references().enumerate() {
+            assert_eq!(edge.id(), id);
+        }
+
+        let mut dfs = Dfs::new(&graph, start);
+        let mut reached = 0;
+        while dfs.next(&graph).is_some() {
+            reached += 1;
+        }
+        assert_eq!(reached, graph.node_count());
+
+        // every move removes a peg, so the distance is the difference in peg count
+        let dist = dijkstra(&graph, start, None, |_| 1_u32);
+        for (idx, d) in dist {
+            assert_eq!(d, 5 - graph.seed(idx).count_ones());
+        }
+    }
+
+    #[test]
+    fn test_arrays() {
+        let graph = StateGraph::build_partial(vec![Pyramide::init_from_seed(4, 65534, false)]);
+        let seeds = graph.node_seeds();
+        assert_eq!(seeds.len(), graph.node_count());
+        assert!(seeds.windows(2).all(|pair| pair[0] < pair[1]));
+        let pairs = graph.edge_pairs();
+        assert_eq!((pairs.len(), graph.edge_labels().len()), (graph.edge_count(), graph.edge_count()));
+        for ([source, target], label) in pairs.iter().zip(graph.edge_labels()) {
+            let mut pyra = Pyramide::init_from_seed(4, seeds[*source as usize], false);
+            pyra.coup(&graph.coup(*label)).expect("legal move");
+            assert_eq!(pyra.seed(), seeds[*target as usize]);
+        }
+        assert!(StateGraph::try_build_partial(vec![Pyramide::init_from_seed(3, 230, false)]).is_some());
+    }
+
+    #[test]
+    fn test_full_graph_through_mapped_file() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let graph = StateGraph::build_full(rules);
+        let dir = test_dir("full_graph_file");
+        assert_eq!(StateGraph::build_full_in(&dir, rules).unwrap(), graph);
+        let mut degrees: SeedArray<u16> = SeedArray::open(dir.join("degrees.bin"), rules).unwrap();
+        let dead_ends = (1..0xFFFF).filter(|seed| degrees.get(*seed) == 1).count();
+        assert_eq!(dead_ends, graph.layer_stats().iter().map(|layer| layer.dead_ends).sum::<usize>());
+
+        // interrupted halfway through the degrees
+        for seed in 0x8000..0x10000 {
+            degrees.set(seed, 0);
+        }
+        degrees.flush().unwrap();
+        assert_eq!(StateGraph::build_full_in(&dir, rules).unwrap(), graph);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_full_graph_resumes_from_checkpoint() {
+        let rules = PyramideRules { lines: 3, diag_allowed: true };
+        let dir = test_dir("full_graph");
+        let mut checkpoint = Checkpoint::new(&dir, rules, Duration::ZERO);
+
+        // simulate an interrupted build
+        let mut interrupted = StateGraph::with_seeds(rules, StateGraph::full_seed_index(rules));
+        for seed in 1..=200 {
+            interrupted.push_node(seed);
+        }
+        let mut enc = Encoder::new();
+        interrupted.encode(&mut enc);
+        checkpoint.save(FULL_GRAPH, &enc.finish()).unwrap();
+
+        let resumed = StateGraph::build_full_resumable(&mut checkpoint).unwrap();
+        assert_eq!(resumed, StateGraph::build_full(rules));
+        assert_eq!(checkpoint.load(FULL_GRAPH).unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_partial_graph_resumes_from_checkpoint() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let dir = test_dir("partial_graph");
+        let mut checkpoint = Checkpoint::new(&dir, rules, Duration::ZERO);
+        let starts = vec![Pyramide::init_from_seed(4, 65534, true), Pyramide::init_from_seed(4, 65533, true)];
+        let expected = StateGraph::build_partial(starts.clone());
+
+        // interrupted during the exploration, once everything reachable from 65533 was visited
+        let mut visited: HashSet<Seed> = [65534, 65533].into_iter().collect();
+        explore(rules, &mut visited, &mut vec![65533], |_, _| Ok(())).unwrap();
+        let mut enc = Encoder::new();
+        enc.seeds(&[65533, 65534]);
+        enc.u8(EXPLORING);
+        enc.seeds(&visited.iter().copied().collect::<Vec<Seed>>());
+        enc.seeds(&[65534]);
+        checkpoint.save(PARTIAL_GRAPH, &enc.finish()).unwrap();
+        let resumed = StateGraph::build_partial_resumable(starts.clone(), &mut checkpoint).unwrap();
+        assert_eq!(resumed, expected);
+
+        // interrupted while filling the graph
+        let mut enc = Encoder::new();
+        enc.seeds(&[65533, 65534]);
+        enc.u8(FILLING);
+        let mut graph = StateGraph::with_seeds(rules, expected.seeds.clone());
+        for idx in 0..100 {
+            graph.push_node(graph.seeds.seed(idx));
+        }
+        graph.encode(&mut enc);
+        checkpoint.save(PARTIAL_GRAPH, &enc.finish()).unwrap();
+        let resumed = StateGraph::build_partial_resumable(starts, &mut checkpoint).unwrap();
+        assert_eq!(resumed, expected);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_seed_index() {
+        let range = SeedIndex::Range { start: 1, len: 10 };
+        assert_eq!(range.index(0), None);
+        assert_eq!(range.index(1), Some(0));
+        assert_eq!(range.index(11), None);
+        assert_eq!(range.seed(9), 10);
+        let sorted = SeedIndex::Sorted(vec![3, 7, 42]);
+        assert_eq!(sorted.index(7), Some(1));
+        assert_eq!(sorted.index(8), None);
+        assert_eq!(sorted.seed(2), 42);
+    }
+}
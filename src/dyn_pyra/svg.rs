@@ -0,0 +1,156 @@
+// SVG drawings of a board and of a whole game ("solution sheet"), for the documentation.
+// In a solution sheet every step shows the board before the jump, with the jumping peg,
+// the jumped peg and the landing hole highlighted.
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::solver::Solver;
+use super::{CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+const CELL_SIZE: usize = 40;
+const MARGIN: usize = 30;
+const CAPTION: usize = 24;
+const STEPS_PER_ROW: usize = 4;
+
+const PEG_COLOR: &str = "#444444";
+const JUMPING_COLOR: &str = "#2a7ab8";
+const JUMPED_COLOR: &str = "#d9534f";
+const LANDING_COLOR: &str = "#5cb85c";
+
+pub fn test_svg(rules: PyramideRules, dir: &Path) -> io::Result<()> {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let start = Pyramide::init_from_seed(rules.lines, full_seed & !1, rules.diag_allowed);
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("pyramide.svg"), pyramide_svg(&start))?;
+    if let Some(solution) = Solver::new(&start, None).solve(&start) {
+        let sheet = solution_sheet_svg(&start, &solution).expect("valid solution");
+        fs::write(dir.join("solution.svg"), sheet)?;
+    }
+    Ok(())
+}
+
+fn board_size(lines: usize) -> (usize, usize) {
+    (MARGIN + CELL_SIZE * (2 * lines - 1), MARGIN + CELL_SIZE * lines)
+}
+
+fn cell_center(pyra: &Pyramide, idx: CellIndex) -> (usize, usize) {
+    let (row, col) = pyra.cell_position(idx);
+    (MARGIN + CELL_SIZE * col + CELL_SIZE / 2, MARGIN + CELL_SIZE * row + CELL_SIZE / 2)
+}
+
+fn board_group(pyra: &Pyramide, x: usize, y: usize, highlight: Option<[CellIndex; 3]>) -> String {
+    let mut svg = String::new();
+    writeln!(svg, r#"<g transform="translate({x},{y})" font-family="sans-serif" font-size="14">"#).unwrap();
+    for col in 0..2 * pyra.lines - 1 {
+        let cx = MARGIN + CELL_SIZE * col + CELL_SIZE / 2;
+        writeln!(svg, r#"<text x="{cx}" y="{}" text-anchor="middle">{}</text>"#, MARGIN - 10, col + 1).unwrap();
+    }
+    for row in 0..pyra.lines {
+        let cy = MARGIN + CELL_SIZE * row + CELL_SIZE / 2 + 5;
+        writeln!(svg, r#"<text x="10" y="{cy}" text-anchor="middle">{}</text>"#, (b'a' + row as u8) as char).unwrap();
+    }
+    let radius = CELL_SIZE * 3 / 8;
+    for (idx, cell) in pyra.iter().enumerate() {
+        let (cx, cy) = cell_center(pyra, idx);
+        let (fill, stroke) = match (highlight, cell) {
+            (Some([dep, _, _]), _) if dep == idx => (JUMPING_COLOR, JUMPING_COLOR),
+            (Some([_, mil, _]), _) if mil == idx => (JUMPED_COLOR, JUMPED_COLOR),
+            (Some([_, _, arr]), _) if arr == idx => ("white", LANDING_COLOR),
+            (_, Some(true)) => (PEG_COLOR, PEG_COLOR),
+            _ => ("white", PEG_COLOR),
+        };
+        writeln!(svg, r#"<circle cx="{cx}" cy="{cy}" r="{radius}" fill="{fill}" stroke="{stroke}" stroke-width="2"/>"#).unwrap();
+    }
+    svg.push_str("</g>\n");
+    svg
+}
+
+fn svg_document(width: usize, height: usize, body: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n{body}</svg>\n"
+    )
+}
+
+pub fn pyramide_svg(pyra: &Pyramide) -> String {
+    let (width, height) = board_size(pyra.lines);
+    svg_document(width + MARGIN, height + MARGIN, &board_group(pyra, 0, 0, None))
+}
+
+pub fn solution_sheet_svg(start: &Pyramide, coups: &[Coup]) -> Result<String, ()> {
+    // one board per move plus the final board, STEPS_PER_ROW boards per row
+    let (board_width, board_height) = board_size(start.lines);
+    let (step_width, step_height) = (board_width + MARGIN, board_height + CAPTION + MARGIN);
+    let mut body = String::new();
+    let mut pyra = start.clone();
+    for step in 0..=coups.len() {
+        let x = (step % STEPS_PER_ROW) * step_width;
+        let y = (step / STEPS_PER_ROW) * step_height;
+        let (highlight, caption) = match coups.get(step) {
+            Some(coup) => {
+                if !pyra.is_coup_valid(coup) {
+                    return Err(())
+                }
+                let name = pyra.coup_name(coup).expect("valid move");
+                (pyra.coup_cells(coup), format!("{}. {}", step + 1, name))
+            },
+            None => (None, "Final position".to_string()),
+        };
+        body.push_str(&board_group(&pyra, x, y, highlight));
+        writeln!(body, r#"<text x="{}" y="{}" font-family="sans-serif" font-size="16" text-anchor="middle">{}</text>"#,
+            x + board_width / 2 + MARGIN / 2, y + board_height + CAPTION, caption).unwrap();
+        if let Some(coup) = coups.get(step) {
+            pyra.coup(coup)?;
+        }
+    }
+    let steps = coups.len() + 1;
+    let width = step_width * steps.min(STEPS_PER_ROW);
+    let height = step_height * steps.div_ceil(STEPS_PER_ROW);
+    Ok(svg_document(width, height, &body))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dyn_pyra::{Direction, Orientation};
+
+    #[test]
+    fn test_pyramide_svg() {
+        // _ _ F
+        // _ T T F
+        // F T T T F
+        // seed : 230
+        let pyra = Pyramide::init_from_seed(3, 230, false);
+        let svg = pyramide_svg(&pyra);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<circle").count(), 9);
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", PEG_COLOR)).count(), 5);
+        for label in [">a<", ">b<", ">c<", ">1<", ">5<"] {
+            assert!(svg.contains(label));
+        }
+    }
+
+    #[test]
+    fn test_solution_sheet_svg() {
+        let pyra = Pyramide::init_from_seed(3, 230, false);
+        let coups = [
+            Coup {mil: 2, orientation: Orientation::Horizontal, dir: Direction::Bas},
+            Coup {mil: 5, orientation: Orientation::Horizontal, dir: Direction::Haut},
+        ];
+        let svg = solution_sheet_svg(&pyra, &coups).expect("valid moves");
+        assert_eq!(svg.matches("<g ").count(), 3);
+        assert!(svg.contains("1. b2-b4"));
+        assert!(svg.contains("2. c3-c1"));
+        assert!(svg.contains("Final position"));
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", JUMPED_COLOR)).count(), 2);
+        assert_eq!(svg.matches(&format!("stroke=\"{}\"", LANDING_COLOR)).count(), 2);
+
+        let invalid = [coups[0], coups[0]];
+        assert_eq!(solution_sheet_svg(&pyra, &invalid), Err(()));
+    }
+}
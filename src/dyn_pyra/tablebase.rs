@@ -0,0 +1,293 @@
+// Tablebase over every seed of a board : the fewest pegs that can be left (best achievable
+// outcome) and the set of cells on which a single peg finish is possible. A position without
+// a move keeps its pegs, otherwise it takes the best of its successors. Successors have one peg
+// less, the seeds are handled by increasing number of pegs.
+// The tables are seed arrays, in memory or in memory-mapped files. Filled in files, the tables
+// are flushed and the next seed to handle saved in a checkpoint, to resume after an interruption.
+
+use std::io;
+use std::path::Path;
+
+use indicatif::ProgressBar;
+
+use super::checkpoint::{Checkpoint, Decoder, Encoder};
+use super::seed_array::SeedArray;
+use super::{jumps, CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+// 9 bytes per seed, 288 MiB at 5 lines and 576 GiB at 6
+pub const MAX_TABLEBASE_LINES: usize = 5;
+
+const TABLEBASE: &str = "tablebase";
+const SAVE_CHECK_EVERY: usize = 1 << 16;
+
+pub fn test_tablebase(rules: PyramideRules) {
+    let tablebase = Tablebase::build(rules);
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    for hole in 0..cells_num as CellIndex {
+        let start = full_seed & !(1 << hole);
+        let finals: Vec<String> = tablebase.final_cells(start).into_iter().map(|cell| pyra.cell_name(cell)).collect();
+        println!("Starting hole {} : best {} pegs, single peg finish on {}",
+            pyra.cell_name(hole), tablebase.best_pegs(start), if finals.is_empty() { "-".to_string() } else { finals.join(" ") });
+    }
+}
+
+pub struct Tablebase {
+    rules: PyramideRules,
+    // (departure and middle cells, arrival cell) of every jump
+    jumps: Vec<(Coup, Seed, Seed)>,
+    best: SeedArray<u8>,
+    // bit c set when the position can end with a single peg on cell c
+    finals: SeedArray<u64>,
+}
+
+fn seeds_with_pegs(cells_num: usize, pegs: u32) -> impl Iterator<Item = Seed> {
+    // increasing seeds with pegs bits set (Gosper's hack)
+    let first: Seed = if pegs == 0 { 0 } else { Seed::MAX >> (Seed::BITS - pegs) };
+    let end: Seed = 1 << cells_num;
+    std::iter::successors(Some(first), move |&seed| {
+        if seed == 0 {
+            return None
+        }
+        let low = seed & seed.wrapping_neg();
+        let ripple = seed + low;
+        Some(ripple | (((seed ^ ripple) >> 2) / low))
+    })
+    .take_while(move |seed| *seed < end)
+}
+
+impl Tablebase {
+    fn with_arrays(rules: PyramideRules, best: SeedArray<u8>, finals: SeedArray<u64>) -> Tablebase {
+        let jumps = jumps(rules).into_iter()
+            .map(|(coup, [dep, mil, arr])| (coup, (1 << dep) | (1 << mil), 1 << arr))
+            .collect();
+        Tablebase { rules, jumps, best, finals }
+    }
+
+    fn check_lines(rules: PyramideRules) -> io::Result<()> {
+        if rules.lines > MAX_TABLEBASE_LINES {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("tablebases are limited to {} lines", MAX_TABLEBASE_LINES)))
+        }
+        Ok(())
+    }
+
+    pub fn build(rules: PyramideRules) -> Tablebase {
+        Tablebase::check_lines(rules).expect("tablebase size");
+        let mut tablebase = Tablebase::with_arrays(rules, SeedArray::in_memory(rules), SeedArray::in_memory(rules));
+        tablebase.fill((0, 0), |_, _| Ok(())).expect("nothing to save");
+        tablebase
+    }
+
+    fn create_in(dir: &Path, rules: PyramideRules) -> io::Result<Tablebase> {
+        // never over a finished table, the seed array files would be truncated
+        if ["best.bin", "finals.bin"].iter().any(|name| dir.join(name).exists()) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already holds a tablebase", dir.display())))
+        }
+        Tablebase::check_lines(rules)?;
+        std::fs::create_dir_all(dir)?;
+        let best = SeedArray::create(dir.join("best.bin"), rules)?;
+        let finals = SeedArray::create(dir.join("finals.bin"), rules)?;
+        Ok(Tablebase::with_arrays(rules, best, finals))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.best.flush()?;
+        self.finals.flush()
+    }
+
+    pub fn build_in(dir: &Path, rules: PyramideRules) -> io::Result<Tablebase> {
+        // tables in dir/best.bin and dir/finals.bin, for the boards larger than the RAM, an
+        // existing table is an error
+        let mut tablebase = Tablebase::create_in(dir, rules)?;
+        tablebase.fill((0, 0), |_, _| Ok(()))?;
+        tablebase.flush()?;
+        Ok(tablebase)
+    }
+
+    pub fn build_in_resumable(dir: &Path, checkpoint: &mut Checkpoint) -> io::Result<Tablebase> {
+        // The checkpoint holds the next seed to handle (number of pegs, seed), the tables
+        // filled up to it are in their files
+        let rules = checkpoint.rules();
+        let (mut tablebase, from) = match checkpoint.load(TABLEBASE)? {
+            Some(data) => {
+                let mut dec = Decoder::new(&data);
+                let from = (dec.u32()?, dec.seed()?);
+                (Tablebase::open(dir, rules)?, from)
+            },
+            None => (Tablebase::create_in(dir, rules)?, (0, 0)),
+        };
+        tablebase.fill(from, |tablebase, (pegs, seed)| {
+            if !checkpoint.is_due() {
+                return Ok(())
+            }
+            tablebase.flush()?;
+            let mut enc = Encoder::new();
+            enc.u32(pegs);
+            enc.seed(seed);
+            checkpoint.save(TABLEBASE, &enc.finish())
+        })?;
+        tablebase.flush()?;
+        checkpoint.clear(TABLEBASE)?;
+        Ok(tablebase)
+    }
+
+    pub fn open(dir: &Path, rules: PyramideRules) -> io::Result<Tablebase> {
+        Tablebase::check_lines(rules)?;
+        let best = SeedArray::open(dir.join("best.bin"), rules)?;
+        let finals = SeedArray::open(dir.join("finals.bin"), rules)?;
+        Ok(Tablebase::with_arrays(rules, best, finals))
+    }
+
+    fn successors(&self, seed: Seed) -> impl Iterator<Item = (Coup, Seed)> + '_ {
+        self.jumps.iter()
+            .filter(move |(_, from, arrival)| seed & from == *from && seed & arrival == 0)
+            .map(move |(coup, from, arrival)| (*coup, seed & !from | arrival))
+    }
+
+    fn fill(&mut self, from: (u32, Seed), mut save: impl FnMut(&Tablebase, (u32, Seed)) -> io::Result<()>) -> io::Result<()> {
+        // seeds from (pegs, seed) from on, save is called now and then with the next seed to handle
+        let cells_num = self.rules.lines * self.rules.lines;
+        let prog_bar = ProgressBar::new(1 << cells_num);
+        let mut handled = 0_usize;
+        for pegs in 0..=cells_num as u32 {
+            for seed in seeds_with_pegs(cells_num, pegs) {
+                if (pegs, seed) < from {
+                    prog_bar.inc(1);
+                    continue;
+                }
+                if handled.is_multiple_of(SAVE_CHECK_EVERY) {
+                    save(self, (pegs, seed))?;
+                }
+                handled += 1;
+                let (mut best, mut finals) = (pegs as u8, if pegs == 1 { seed as u64 } else { 0 });
+                for (_, next) in self.successors(seed) {
+                    best = best.min(self.best.get(next));
+                    finals |= self.finals.get(next);
+                }
+                self.best.set(seed, best);
+                self.finals.set(seed, finals);
+                prog_bar.inc(1);
+            }
+        }
+        prog_bar.finish();
+        Ok(())
+    }
+
+    pub fn rules(&self) -> PyramideRules {
+        self.rules
+    }
+
+    pub fn best_pegs(&self, seed: Seed) -> u8 {
+        // fewest pegs that can be left from seed
+        self.best.get(seed)
+    }
+
+    pub fn final_cells(&self, seed: Seed) -> Vec<CellIndex> {
+        let finals = self.finals.get(seed);
+        (0..self.rules.lines * self.rules.lines).filter(|cell| finals & (1 << cell) != 0).collect()
+    }
+
+    pub fn can_finish_on(&self, seed: Seed, cell: CellIndex) -> bool {
+        self.finals.get(seed) & (1 << cell) != 0
+    }
+
+    pub fn is_winnable(&self, seed: Seed) -> bool {
+        self.finals.get(seed) != 0
+    }
+
+    pub fn best_coup(&self, seed: Seed) -> Option<Coup> {
+        // a move keeping the best outcome
+        let best = self.best_pegs(seed);
+        self.successors(seed).find(|(_, next)| self.best_pegs(*next) == best).map(|(coup, _)| coup)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::dyn_pyra::checkpoint::test_dir;
+    use crate::dyn_pyra::deepening::{DeepeningSolver, MoveOrder};
+    use crate::dyn_pyra::retrograde::winnable_seeds;
+    use crate::dyn_pyra::state_graph::StateGraph;
+
+    #[test]
+    fn test_seeds_with_pegs() {
+        let seeds: Vec<Seed> = seeds_with_pegs(4, 2).collect();
+        assert_eq!(seeds, vec![0b0011, 0b0101, 0b0110, 0b1001, 0b1010, 0b1100]);
+        assert_eq!(seeds_with_pegs(4, 0).collect::<Vec<Seed>>(), vec![0]);
+        assert_eq!(seeds_with_pegs(4, 4).collect::<Vec<Seed>>(), vec![0b1111]);
+        assert_eq!((0..=9).map(|pegs| seeds_with_pegs(9, pegs).count()).sum::<usize>(), 512);
+    }
+
+    #[test]
+    fn test_tablebase() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let tablebase = Tablebase::build(rules);
+        let winnable = winnable_seeds(rules);
+        assert!((0..1 << 16).all(|seed| tablebase.is_winnable(seed) == winnable.contains(seed as usize)));
+
+        let mut solver = DeepeningSolver::new(rules, MoveOrder::Natural, 1 << 12);
+        for hole in [0, 5, 12] {
+            let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << hole), false);
+            assert_eq!(tablebase.best_pegs(start.seed()) as usize, 15 - solver.deepen(&start).len());
+            // playing the best moves reaches the best outcome
+            let mut seed = start.seed();
+            while let Some(coup) = tablebase.best_coup(seed) {
+                let mut pyra = Pyramide::init_from_seed(4, seed, false);
+                pyra.coup(&coup).expect("legal move");
+                seed = pyra.seed();
+            }
+            assert_eq!(seed.count_ones(), tablebase.best_pegs(start.seed()) as u32);
+        }
+    }
+
+    #[test]
+    fn test_final_cells_and_files() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let dir = test_dir("tablebase");
+        Tablebase::build_in(&dir, rules).unwrap();
+        assert_eq!(Tablebase::build_in(&dir, rules).err().map(|err| err.kind()), Some(io::ErrorKind::AlreadyExists));
+        let too_large = PyramideRules { lines: 6, diag_allowed: true };
+        assert_eq!(Tablebase::build_in(&dir.join("6"), too_large).err().map(|err| err.kind()), Some(io::ErrorKind::InvalidInput));
+        let tablebase = Tablebase::open(&dir, rules).unwrap();
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let graph = StateGraph::build_partial(vec![start.clone()]);
+        let reached: Vec<CellIndex> = (0..16).filter(|cell| graph.index(1 << cell).is_some()).collect();
+        assert_eq!(tablebase.final_cells(start.seed()), reached);
+        assert!(tablebase.can_finish_on(start.seed(), reached[0]));
+        assert_eq!(tablebase.best_pegs(start.seed()), 1);
+        assert_eq!(tablebase.best_pegs(1 | (1 << 15)), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let rules = PyramideRules { lines: 4, diag_allowed: false };
+        let expected = Tablebase::build(rules);
+        let dir = test_dir("tablebase_resume");
+        let mut checkpoint = Checkpoint::new(dir.join("checkpoints"), rules, Duration::ZERO);
+        // interrupted before the positions of 8 pegs
+        {
+            let mut tablebase = Tablebase::build_in(&dir, rules).unwrap();
+            for seed in (0..1 << 16).filter(|seed: &Seed| seed.count_ones() >= 8) {
+                tablebase.best.set(seed, 0);
+                tablebase.finals.set(seed, 0);
+            }
+            tablebase.flush().unwrap();
+        }
+        let mut enc = Encoder::new();
+        enc.u32(8);
+        enc.seed(0xFF);
+        checkpoint.save(TABLEBASE, &enc.finish()).unwrap();
+
+        let tablebase = Tablebase::build_in_resumable(&dir, &mut checkpoint).unwrap();
+        assert!((0..1 << 16).all(|seed| tablebase.best_pegs(seed) == expected.best_pegs(seed)
+            && tablebase.final_cells(seed) == expected.final_cells(seed)));
+        assert_eq!(checkpoint.load(TABLEBASE).unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
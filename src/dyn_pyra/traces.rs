@@ -0,0 +1,233 @@
+// Solutions up to the order of independent moves. Two moves whose three cells are disjoint
+// commute : playing them in either order gives the same position, and neither one changes
+// whether the other is legal. Solutions differing only by such swaps form a class (a trace),
+// represented by its lexicographically smallest sequence in the order of the moves of the rules.
+// The classes are counted with sleep sets : after a move has been tried from a position, the
+// later siblings independent of it keep it asleep, so each class is followed exactly once.
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
+use super::{jumps, CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+pub fn test_traces(rules: PyramideRules) {
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    let mut solver = TraceSolver::new(rules);
+    for hole in 0..cells_num as CellIndex {
+        let start = full_seed & !(1 << hole);
+        let (solutions, classes) = (solver.solutions(start), solver.classes(start));
+        println!("Starting hole {} : {} solutions, {} essentially different", pyra.cell_name(hole), solutions, classes);
+        if let Some(solution) = solver.representatives(start, 1).first() {
+            let names: Vec<String> = solution.iter().filter_map(|coup| pyra.coup_name(coup)).collect();
+            println!("  {}", names.join(" "));
+        }
+    }
+}
+
+struct Jump {
+    coup: Coup,
+    // departure and middle cells
+    from: Seed,
+    arrival: Seed,
+}
+
+impl Jump {
+    fn cells(&self) -> Seed {
+        self.from | self.arrival
+    }
+}
+
+type JumpIndex = u16;
+
+pub struct TraceSolver {
+    jumps: Vec<Jump>,
+    solutions: HashMap<Seed, BigUint>,
+    // by position and sleep set (sorted)
+    classes: HashMap<(Seed, Vec<JumpIndex>), BigUint>,
+}
+
+impl TraceSolver {
+    pub fn new(rules: PyramideRules) -> TraceSolver {
+        let jumps = jumps(rules).into_iter()
+            .map(|(coup, [dep, mil, arr])| Jump { coup, from: (1 << dep) | (1 << mil), arrival: 1 << arr })
+            .collect();
+        TraceSolver { jumps, solutions: HashMap::new(), classes: HashMap::new() }
+    }
+
+    fn index(&self, coup: &Coup) -> JumpIndex {
+        self.jumps.iter().position(|jump| jump.coup == *coup).expect("a move of the rules") as JumpIndex
+    }
+
+    fn independent(&self, a: JumpIndex, b: JumpIndex) -> bool {
+        self.jumps[a as usize].cells() & self.jumps[b as usize].cells() == 0
+    }
+
+    pub fn commute(&self, a: &Coup, b: &Coup) -> bool {
+        self.independent(self.index(a), self.index(b))
+    }
+
+    fn legal(&self, seed: Seed) -> Vec<JumpIndex> {
+        (0..self.jumps.len() as JumpIndex)
+            .filter(|j| {
+                let jump = &self.jumps[*j as usize];
+                seed & jump.from == jump.from && seed & jump.arrival == 0
+            })
+            .collect()
+    }
+
+    fn play(&self, seed: Seed, j: JumpIndex) -> Seed {
+        let jump = &self.jumps[j as usize];
+        seed & !jump.from | jump.arrival
+    }
+
+    pub fn canonical(&self, solution: &[Coup]) -> Vec<Coup> {
+        // smallest sequence of the class : the smallest move that commutes with every move
+        // before it goes first, and so on
+        let mut rest: Vec<JumpIndex> = solution.iter().map(|coup| self.index(coup)).collect();
+        let mut canonical = vec![];
+        while !rest.is_empty() {
+            let pos = (0..rest.len())
+                .filter(|pos| rest[..*pos].iter().all(|before| self.independent(*before, rest[*pos])))
+                .min_by_key(|pos| rest[*pos])
+                .expect("the first move can always go first");
+            canonical.push(self.jumps[rest.remove(pos) as usize].coup);
+        }
+        canonical
+    }
+
+    pub fn solutions(&mut self, seed: Seed) -> BigUint {
+        // raw number of winning sequences
+        if seed.count_ones() == 1 {
+            return BigUint::from(1_u32)
+        }
+        if let Some(count) = self.solutions.get(&seed) {
+            return count.clone()
+        }
+        let count = self.legal(seed).into_iter().map(|j| self.solutions(self.play(seed, j))).sum::<BigUint>();
+        self.solutions.insert(seed, count.clone());
+        count
+    }
+
+    fn next_sleep(&self, sleep: &[JumpIndex], done: &[JumpIndex], j: JumpIndex) -> Vec<JumpIndex> {
+        let mut next: Vec<JumpIndex> = sleep.iter().chain(done).copied().filter(|other| self.independent(*other, j)).collect();
+        next.sort_unstable();
+        next
+    }
+
+    fn classes_asleep(&mut self, seed: Seed, sleep: Vec<JumpIndex>) -> BigUint {
+        if seed.count_ones() == 1 {
+            return BigUint::from(1_u32)
+        }
+        let key = (seed, sleep);
+        if let Some(count) = self.classes.get(&key) {
+            return count.clone()
+        }
+        let mut count = BigUint::ZERO;
+        let mut done = vec![];
+        for j in self.legal(seed) {
+            if key.1.contains(&j) {
+                continue;
+            }
+            let next_sleep = self.next_sleep(&key.1, &done, j);
+            count += self.classes_asleep(self.play(seed, j), next_sleep);
+            done.push(j);
+        }
+        self.classes.insert(key, count.clone());
+        count
+    }
+
+    pub fn classes(&mut self, seed: Seed) -> BigUint {
+        // number of essentially different solutions
+        self.classes_asleep(seed, vec![])
+    }
+
+    pub fn representatives(&self, seed: Seed, limit: usize) -> Vec<Vec<Coup>> {
+        // canonical solution of the first classes, at most limit
+        let mut found = vec![];
+        self.collect(seed, &[], &mut vec![], &mut found, limit);
+        found
+    }
+
+    fn collect(&self, seed: Seed, sleep: &[JumpIndex], line: &mut Vec<Coup>, found: &mut Vec<Vec<Coup>>, limit: usize) {
+        if seed.count_ones() == 1 {
+            found.push(line.clone());
+            return
+        }
+        let mut done = vec![];
+        for j in self.legal(seed) {
+            if found.len() >= limit {
+                return
+            }
+            if sleep.contains(&j) {
+                continue;
+            }
+            line.push(self.jumps[j as usize].coup);
+            self.collect(self.play(seed, j), &self.next_sleep(sleep, &done, j), line, found, limit);
+            line.pop();
+            done.push(j);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::dyn_pyra::paths::WinningPaths;
+    use crate::dyn_pyra::state_graph::StateGraph;
+
+    fn all_solutions(solver: &TraceSolver, seed: Seed, line: &mut Vec<Coup>, found: &mut Vec<Vec<Coup>>) {
+        if seed.count_ones() == 1 {
+            found.push(line.clone());
+        }
+        for j in solver.legal(seed) {
+            line.push(solver.jumps[j as usize].coup);
+            all_solutions(solver, solver.play(seed, j), line, found);
+            line.pop();
+        }
+    }
+
+    #[test]
+    fn test_classes() {
+        let rules = PyramideRules { lines: 4, diag_allowed: true };
+        let mut solver = TraceSolver::new(rules);
+        // a few moves into a game, every solution can be listed
+        let mut start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << 4), true);
+        for coup in &solver.representatives(start.seed(), 1)[0][..4] {
+            start.coup(coup).expect("legal move");
+        }
+        let mut solutions = vec![];
+        all_solutions(&solver, start.seed(), &mut vec![], &mut solutions);
+        assert_eq!(solver.solutions(start.seed()), BigUint::from(solutions.len()));
+        let graph = StateGraph::build_partial(vec![start.clone()]);
+        assert_eq!(WinningPaths::count(&graph, &[start.seed()]).starts[0], solver.solutions(start.seed()));
+
+        let classes = solver.classes(start.seed());
+        let indexes = |line: &[Coup]| -> Vec<JumpIndex> { line.iter().map(|coup| solver.index(coup)).collect() };
+        let canonical: HashSet<Vec<JumpIndex>> = solutions.iter().map(|solution| indexes(&solver.canonical(solution))).collect();
+        assert_eq!(BigUint::from(canonical.len()), classes);
+        assert!(canonical.len() < solutions.len());
+        let representatives = solver.representatives(start.seed(), usize::MAX);
+        assert_eq!(representatives.len(), canonical.len());
+        for representative in &representatives {
+            assert!(canonical.contains(&indexes(representative)));
+            assert_eq!(&solver.canonical(representative), representative);
+        }
+        assert_eq!(solver.representatives(start.seed(), 3).len(), 3);
+    }
+
+    #[test]
+    fn test_commute() {
+        let solver = TraceSolver::new(PyramideRules { lines: 4, diag_allowed: false });
+        let pyra = Pyramide::new(4, false);
+        let coup = |name: &str| pyra.coup_from_name(name).expect("a move");
+        assert!(solver.commute(&coup("c4-a4"), &coup("d1-d3")));
+        assert!(!solver.commute(&coup("c4-a4"), &coup("c2-c4")));
+        let swapped = [coup("c4-a4"), coup("d1-d3")];
+        assert_eq!(solver.canonical(&swapped), solver.canonical(&[swapped[1], swapped[0]]));
+    }
+}
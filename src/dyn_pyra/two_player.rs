@@ -0,0 +1,292 @@
+// Two-player variant : the players alternate jumps on the same board, the player who can not move
+// loses (normal play) or wins (misère play). The game is impartial, the outcome of a position
+// only depends on its seed : the player to move wins if a move leads to a position lost for the
+// opponent.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use fixedbitset::FixedBitSet;
+
+use super::state_graph::{StateGraph, StateIndex};
+use super::{cherche_coups_possibles, coups_theoriques, CellIndex, Coup, Pyramide, PyramideRules, Seed};
+
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Eq)]
+pub enum PlayRule {
+    // the player who can not move loses
+    Normal,
+    // the player who can not move wins
+    Misere,
+}
+
+#[derive(Debug, PartialEq, Hash, Clone, Copy, Eq)]
+pub enum Player {
+    First,
+    Second,
+}
+
+impl Player {
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::First => Player::Second,
+            Player::Second => Player::First,
+        }
+    }
+}
+
+pub fn test_two_player(rules: PyramideRules) {
+    let pyra = Pyramide::new(rules.lines, rules.diag_allowed);
+    for play_rule in [PlayRule::Normal, PlayRule::Misere] {
+        println!("{:?} play", play_rule);
+        for (hole, winner) in starting_hole_winners(rules, play_rule) {
+            println!("Starting hole {} : {:?} player wins", pyra.cell_name(hole), winner);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameState {
+    pyra: Pyramide,
+    to_move: Player,
+}
+
+impl GameState {
+    pub fn new(pyra: Pyramide) -> GameState {
+        GameState { pyra, to_move: Player::First }
+    }
+
+    pub fn pyramide(&self) -> &Pyramide {
+        &self.pyra
+    }
+
+    pub fn to_move(&self) -> Player {
+        self.to_move
+    }
+
+    pub fn legal_coups(&self) -> Vec<Coup> {
+        cherche_coups_possibles(&self.pyra, &coups_theoriques(&self.pyra)).unwrap_or_default()
+    }
+
+    pub fn play(&mut self, coup: &Coup) -> Result<(),()> {
+        self.pyra.coup(coup)?;
+        self.to_move = self.to_move.opponent();
+        Ok(())
+    }
+
+    pub fn winner(&self, play_rule: PlayRule) -> Option<Player> {
+        // None while a move is possible
+        if !self.legal_coups().is_empty() {
+            return None
+        }
+        match play_rule {
+            PlayRule::Normal => Some(self.to_move.opponent()),
+            PlayRule::Misere => Some(self.to_move),
+        }
+    }
+}
+
+// Negamax with a table of the positions already solved
+pub struct TwoPlayerSolver {
+    play_rule: PlayRule,
+    coups: Vec<Coup>,
+    // true when the player to move wins
+    wins: HashMap<Seed, bool>,
+}
+
+impl TwoPlayerSolver {
+    pub fn new(rules: PyramideRules, play_rule: PlayRule) -> TwoPlayerSolver {
+        let coups = coups_theoriques(&Pyramide::new(rules.lines, rules.diag_allowed));
+        TwoPlayerSolver { play_rule, coups, wins: HashMap::new() }
+    }
+
+    pub fn solved_positions(&self) -> usize {
+        self.wins.len()
+    }
+
+    pub fn to_move_wins(&mut self, pyra: &Pyramide) -> bool {
+        let seed = pyra.seed();
+        if let Some(wins) = self.wins.get(&seed) {
+            return *wins
+        }
+        let coups = cherche_coups_possibles(pyra, &self.coups);
+        let wins = match coups {
+            None => self.play_rule == PlayRule::Misere,
+            Some(coups) => coups.iter().any(|coup| {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(coup).expect("already checked");
+                !self.to_move_wins(&new_pyra)
+            }),
+        };
+        self.wins.insert(seed, wins);
+        return wins
+    }
+
+    pub fn winner(&mut self, state: &GameState) -> Player {
+        if self.to_move_wins(&state.pyra) { state.to_move } else { state.to_move.opponent() }
+    }
+
+    pub fn best_coup(&mut self, pyra: &Pyramide) -> Option<Coup> {
+        // a winning move if there is one, else any legal move
+        let coups = cherche_coups_possibles(pyra, &self.coups)?;
+        let winning = coups.iter().copied().find(|coup| {
+            let mut new_pyra = pyra.clone();
+            new_pyra.coup(coup).expect("already checked");
+            !self.to_move_wins(&new_pyra)
+        });
+        return winning.or(coups.first().copied())
+    }
+}
+
+pub fn starting_hole_winners(rules: PyramideRules, play_rule: PlayRule) -> Vec<(CellIndex, Player)> {
+    // winner for every starting hole, the first player makes the first jump
+    let cells_num = rules.lines * rules.lines;
+    let full_seed: Seed = (1 << cells_num) - 1;
+    let mut solver = TwoPlayerSolver::new(rules, play_rule);
+    (0..cells_num)
+        .map(|hole| {
+            let start = Pyramide::init_from_seed(rules.lines, full_seed & !(1 << hole), rules.diag_allowed);
+            (hole, solver.winner(&GameState::new(start)))
+        })
+        .collect()
+}
+
+pub fn winning_nodes(graph: &StateGraph, play_rule: PlayRule) -> FixedBitSet {
+    // retrograde version over a whole state graph : bit idx is set when the player to move wins.
+    // Moves remove a peg, positions are solved by increasing number of pegs.
+    let mut nodes: Vec<StateIndex> = (0..graph.node_count() as StateIndex).collect();
+    nodes.sort_by_key(|idx| graph.seed(*idx).count_ones());
+    let mut wins = FixedBitSet::with_capacity(graph.node_count());
+    for idx in nodes {
+        let targets = graph.neighbors_slice(idx);
+        let win = if targets.is_empty() {
+            play_rule == PlayRule::Misere
+        } else {
+            targets.iter().any(|target| !wins.contains(*target as usize))
+        };
+        wins.set(idx as usize, win);
+    }
+    return wins
+}
+
+pub fn play_against_engine<R: BufRead, W: Write>(
+    start: Pyramide,
+    play_rule: PlayRule,
+    human: Player,
+    input: R,
+    mut output: W,
+) -> io::Result<Player> {
+    // moves are read as names ("c4-a4"), one per line, returns the winner
+    let mut solver = TwoPlayerSolver::new(start.rules(), play_rule);
+    let mut state = GameState::new(start);
+    let mut lines = input.lines();
+    let mut last_coup: Option<Coup> = None;
+    loop {
+        let display = match last_coup {
+            Some(coup) => state.pyra.display().last_move(coup),
+            None => state.pyra.display(),
+        };
+        writeln!(output, "{}", display)?;
+        if let Some(winner) = state.winner(play_rule) {
+            writeln!(output, "{:?} player wins", winner)?;
+            return Ok(winner)
+        }
+        let coup = if state.to_move == human {
+            write!(output, "Your move : ")?;
+            output.flush()?;
+            let Some(line) = lines.next() else {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more moves"))
+            };
+            let line = line?;
+            match state.pyra.coup_from_name(line.trim()).filter(|coup| state.pyra.is_coup_valid(coup)) {
+                Some(coup) => coup,
+                None => {
+                    writeln!(output, "Illegal move {}", line.trim())?;
+                    continue;
+                },
+            }
+        } else {
+            let coup = solver.best_coup(&state.pyra).expect("game not over");
+            writeln!(output, "Engine plays {}", state.pyra.coup_name(&coup).expect("legal move"))?;
+            coup
+        };
+        state.play(&coup).expect("legal move");
+        last_coup = Some(coup);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solver_matches_retrograde() {
+        for play_rule in [PlayRule::Normal, PlayRule::Misere] {
+            for diag_allowed in [false, true] {
+                let rules = PyramideRules { lines: 4, diag_allowed };
+                let start = Pyramide::init_from_seed(4, 0xFFFF & !(1 << 4), diag_allowed);
+                let graph = StateGraph::build_partial(vec![start]);
+                let wins = winning_nodes(&graph, play_rule);
+                let mut solver = TwoPlayerSolver::new(rules, play_rule);
+                for idx in 0..graph.node_count() as StateIndex {
+                    assert_eq!(solver.to_move_wins(&graph.pyramide(idx)), wins.contains(idx as usize));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_positions() {
+        // a single move left : the first player makes it and the second can not move
+        let pyra = Pyramide::init_from_seed(3, 0b1_1000_0000, false);
+        let state = GameState::new(pyra.clone());
+        let rules = PyramideRules { lines: 3, diag_allowed: false };
+        assert_eq!(TwoPlayerSolver::new(rules, PlayRule::Normal).winner(&state), Player::First);
+        assert_eq!(TwoPlayerSolver::new(rules, PlayRule::Misere).winner(&state), Player::Second);
+        assert_eq!(state.winner(PlayRule::Normal), None);
+
+        // plain minimax without any table, over the whole game tree
+        fn to_move_wins(pyra: &Pyramide, play_rule: PlayRule) -> bool {
+            let coups = cherche_coups_possibles(pyra, &coups_theoriques(pyra)).unwrap_or_default();
+            if coups.is_empty() {
+                return play_rule == PlayRule::Misere
+            }
+            coups.iter().any(|coup| {
+                let mut new_pyra = pyra.clone();
+                new_pyra.coup(coup).expect("legal move");
+                !to_move_wins(&new_pyra, play_rule)
+            })
+        }
+        for play_rule in [PlayRule::Normal, PlayRule::Misere] {
+            for diag_allowed in [false, true] {
+                let rules = PyramideRules { lines: 3, diag_allowed };
+                let winners = starting_hole_winners(rules, play_rule);
+                assert_eq!(winners.len(), 9);
+                for (hole, winner) in winners {
+                    let start = Pyramide::init_from_seed(3, 0x1FF & !(1 << hole), diag_allowed);
+                    let expected = if to_move_wins(&start, play_rule) { Player::First } else { Player::Second };
+                    assert_eq!(winner, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_play_against_engine() {
+        // the engine plays the only move, the human can not answer
+        let pyra = Pyramide::init_from_seed(3, 0b1_1000_0000, false);
+        let mut output = vec![];
+        let winner = play_against_engine(pyra.clone(), PlayRule::Normal, Player::Second, "".as_bytes(), &mut output)
+            .expect("game played");
+        assert_eq!(winner, Player::First);
+        let text = String::from_utf8(output).expect("utf8");
+        assert!(text.contains("Engine plays c5-c3"));
+
+        // the human plays it, after an illegal try
+        let mut output = vec![];
+        let winner = play_against_engine(pyra, PlayRule::Misere, Player::First, "c1-c3\nc5-c3\n".as_bytes(), &mut output)
+            .expect("game played");
+        assert_eq!(winner, Player::Second);
+        assert!(String::from_utf8(output).expect("utf8").contains("Illegal move c1-c3"));
+    }
+}
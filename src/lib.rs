@@ -0,0 +1,11 @@
+#![allow(clippy::needless_return)]
+// game operations report an invalid move with Result<_, ()>
+#![allow(clippy::result_unit_err)]
+
+pub mod dyn_pyra;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+mod python;
@@ -1,9 +1,34 @@
+#![allow(clippy::needless_return)]
+
+// first solver, kept as written
+#[allow(dead_code, unreachable_code, unused_imports, clippy::bool_comparison, clippy::clone_on_copy,
+    clippy::extend_with_drain, clippy::len_zero, clippy::let_and_return, clippy::partialeq_ne_impl,
+    clippy::ptr_arg, clippy::redundant_pattern_matching, clippy::unnecessary_unwrap)]
 mod naive_pyra;
 
 pub use crate::naive_pyra::*;
-mod dyn_pyra;
-use dyn_pyra::PyramideRules;
-use dyn_pyra::test_dyn_graph;
+use triangle_problem::dyn_pyra::PyramideRules;
+use triangle_problem::dyn_pyra::test_dyn_graph;
+use triangle_problem::dyn_pyra::test_state_graph;
+use triangle_problem::dyn_pyra::retrograde::test_retrograde;
+use triangle_problem::dyn_pyra::pagoda::test_pagoda;
+use triangle_problem::dyn_pyra::classes::test_classes;
+use triangle_problem::dyn_pyra::svg::test_svg;
+use triangle_problem::dyn_pyra::animation::test_animation;
+use triangle_problem::dyn_pyra::two_player::{play_against_engine, test_two_player, PlayRule, Player};
+use triangle_problem::dyn_pyra::Pyramide;
+use triangle_problem::dyn_pyra::grundy::test_grundy;
+use triangle_problem::dyn_pyra::puzzle::test_puzzle;
+use triangle_problem::dyn_pyra::playouts::test_playouts;
+use triangle_problem::dyn_pyra::nested::test_nested;
+use triangle_problem::dyn_pyra::deepening::test_deepening;
+use triangle_problem::dyn_pyra::beam::test_beam;
+use triangle_problem::dyn_pyra::paths::test_winning_paths;
+use triangle_problem::dyn_pyra::traces::test_traces;
+use triangle_problem::dyn_pyra::seed_array::test_seed_array;
+use triangle_problem::dyn_pyra::external_bfs::test_external_bfs;
+use triangle_problem::dyn_pyra::tablebase::test_tablebase;
+use triangle_problem::dyn_pyra::region_solver::test_region_solver;
 
 fn main() {
 
@@ -19,5 +44,87 @@ fn main() {
         test_full_graph();
     }
 
+    if false {
+        test_state_graph(PyramideRules{lines:5, diag_allowed: true});
+    }
+
+    if false {
+        test_retrograde(PyramideRules{lines:5, diag_allowed: true});
+    }
+
+    if false {
+        test_pagoda(PyramideRules{lines:4, diag_allowed: false});
+    }
+
+    if false {
+        test_classes(PyramideRules{lines:4, diag_allowed: true});
+    }
+
+    if false {
+        test_svg(PyramideRules{lines:4, diag_allowed: true}, std::path::Path::new("doc")).expect("svg written");
+    }
+
+    if false {
+        test_animation(PyramideRules{lines:4, diag_allowed: true}, std::path::Path::new("doc")).expect("gif written");
+    }
+
+    if false {
+        test_two_player(PyramideRules{lines:4, diag_allowed: true});
+    }
+
+    if false {
+        let start = Pyramide::init_from_seed(4, 0xFFFF & !1, true);
+        let stdin = std::io::stdin();
+        play_against_engine(start, PlayRule::Normal, Player::First, stdin.lock(), std::io::stdout()).expect("game played");
+    }
+
+    if false {
+        test_grundy(PyramideRules{lines:4, diag_allowed: true});
+    }
+
+    if false {
+        test_puzzle(PyramideRules{lines:5, diag_allowed: false});
+    }
+
+    if false {
+        test_playouts(PyramideRules{lines:7, diag_allowed: true});
+    }
+
+    if false {
+        test_nested(PyramideRules{lines:8, diag_allowed: true}).expect("search checkpointed");
+    }
+
+    if false {
+        test_deepening(PyramideRules{lines:5, diag_allowed: true});
+    }
+
+    if false {
+        test_beam(PyramideRules{lines:7, diag_allowed: true});
+    }
+
+    if false {
+        test_winning_paths(PyramideRules{lines:5, diag_allowed: true});
+    }
+
+    if false {
+        test_traces(PyramideRules{lines:4, diag_allowed: true});
+    }
+
+    if false {
+        test_seed_array(PyramideRules{lines:5, diag_allowed: true}, std::path::Path::new("checkpoints")).expect("seed arrays written");
+    }
+
+    if false {
+        test_external_bfs(PyramideRules{lines:7, diag_allowed: true}, std::path::Path::new("checkpoints")).expect("layers written");
+    }
+
+    if false {
+        test_tablebase(PyramideRules{lines:5, diag_allowed: true});
+    }
+
+    if false {
+        test_region_solver(PyramideRules{lines:5, diag_allowed: false});
+    }
+
     test_dyn_graph(PyramideRules{lines:4, diag_allowed: true});
 }
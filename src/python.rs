@@ -0,0 +1,199 @@
+// Python module over dyn_pyra for the analysis notebooks, built with maturin (pyproject.toml) :
+//   from triangle_problem import PyramideRules, Pyramide, StateGraph
+//   graph = StateGraph.build_full(PyramideRules(4, True))
+//   seeds, edges = graph.seeds(), graph.edges()
+// Graphs are returned as NumPy arrays : seeds (uint64, one per node), edges (uint32 pairs of
+// node indexes) and move labels (uint16, index in move_names()).
+
+use numpy::{PyArray1, PyArray2, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::dyn_pyra::state_graph::{StateGraph, StateIndex};
+use crate::dyn_pyra::{cherche_coups_possibles, coups_theoriques, Pyramide, Seed};
+
+// seeds are exported as uint64
+const MAX_GRAPH_LINES: usize = 8;
+// every seed of the board is a node, a u32 index beyond
+const MAX_FULL_GRAPH_LINES: usize = 5;
+// a seed holds at most 128 cells
+const MAX_LINES: usize = 11;
+
+#[pyclass(name = "PyramideRules", eq, frozen)]
+#[derive(Clone, PartialEq)]
+struct PyRules(crate::dyn_pyra::PyramideRules);
+
+#[pymethods]
+impl PyRules {
+    #[new]
+    fn new(lines: usize, diag_allowed: bool) -> PyResult<PyRules> {
+        if !(1..=MAX_LINES).contains(&lines) {
+            return Err(PyValueError::new_err(format!("lines must be between 1 and {}", MAX_LINES)))
+        }
+        Ok(PyRules(crate::dyn_pyra::PyramideRules { lines, diag_allowed }))
+    }
+
+    #[getter]
+    fn lines(&self) -> usize {
+        self.0.lines
+    }
+
+    #[getter]
+    fn diag_allowed(&self) -> bool {
+        self.0.diag_allowed
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PyramideRules({}, {})", self.0.lines, if self.0.diag_allowed { "True" } else { "False" })
+    }
+}
+
+#[pyclass(name = "Pyramide")]
+#[derive(Clone)]
+struct PyPyramide(Pyramide);
+
+#[pymethods]
+impl PyPyramide {
+    #[staticmethod]
+    fn from_seed(rules: &PyRules, seed: Seed) -> PyResult<PyPyramide> {
+        let cells_num = rules.0.lines * rules.0.lines;
+        if cells_num < Seed::BITS as usize && seed >> cells_num != 0 {
+            return Err(PyValueError::new_err(format!("seed has more than {} cells", cells_num)))
+        }
+        Ok(PyPyramide(Pyramide::init_from_seed(rules.0.lines, seed, rules.0.diag_allowed)))
+    }
+
+    fn rules(&self) -> PyRules {
+        PyRules(self.0.rules())
+    }
+
+    fn seed(&self) -> Seed {
+        self.0.seed()
+    }
+
+    fn legal_moves(&self) -> Vec<String> {
+        cherche_coups_possibles(&self.0, &coups_theoriques(&self.0))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|coup| self.0.coup_name(coup))
+            .collect()
+    }
+
+    fn apply_move(&mut self, name: &str) -> PyResult<()> {
+        let coup = self.0.coup_from_name(name)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown move {}", name)))?;
+        self.0.coup(&coup).map_err(|_| PyValueError::new_err(format!("illegal move {}", name)))
+    }
+
+    fn is_won(&self) -> bool {
+        self.0.partie_gagne()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        let rules = self.0.rules();
+        format!("Pyramide.from_seed(PyramideRules({}, {}), {})",
+            rules.lines, if rules.diag_allowed { "True" } else { "False" }, self.0.seed())
+    }
+}
+
+#[pyclass(name = "StateGraph", frozen)]
+struct PyStateGraph(StateGraph);
+
+fn check_graph_lines(lines: usize, max_lines: usize) -> PyResult<()> {
+    if lines > max_lines {
+        return Err(PyValueError::new_err(format!("graphs are limited to {} lines", max_lines)))
+    }
+    Ok(())
+}
+
+#[pymethods]
+impl PyStateGraph {
+    #[staticmethod]
+    fn build_full(py: Python<'_>, rules: &PyRules) -> PyResult<PyStateGraph> {
+        check_graph_lines(rules.0.lines, MAX_FULL_GRAPH_LINES)?;
+        let rules = rules.0;
+        Ok(PyStateGraph(py.detach(|| StateGraph::build_full(rules))))
+    }
+
+    #[staticmethod]
+    fn build_partial(py: Python<'_>, starts: Vec<PyPyramide>) -> PyResult<PyStateGraph> {
+        // every position reachable from the starting positions
+        let starts: Vec<Pyramide> = starts.into_iter().map(|pyra| pyra.0).collect();
+        let Some(first) = starts.first() else {
+            return Err(PyValueError::new_err("no starting position"))
+        };
+        let rules = first.rules();
+        check_graph_lines(rules.lines, MAX_GRAPH_LINES)?;
+        if starts.iter().any(|pyra| pyra.rules() != rules) {
+            return Err(PyValueError::new_err("starting positions with different rules"))
+        }
+        py.detach(|| StateGraph::try_build_partial(starts))
+            .map(PyStateGraph)
+            .ok_or_else(|| PyValueError::new_err("too many reachable positions for a u32 index"))
+    }
+
+    fn rules(&self) -> PyRules {
+        PyRules(self.0.rules())
+    }
+
+    fn node_count(&self) -> usize {
+        self.0.node_count()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.0.edge_count()
+    }
+
+    fn index(&self, seed: Seed) -> Option<StateIndex> {
+        self.0.index(seed)
+    }
+
+    fn seeds<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u64>> {
+        let seeds: Vec<u64> = self.0.node_seeds().into_iter().map(|seed| seed as u64).collect();
+        PyArray1::from_vec(py, seeds)
+    }
+
+    fn edges<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u32>>> {
+        // (source, target) node indexes, shape (edge_count, 2)
+        let edges: Vec<StateIndex> = self.0.edge_pairs().into_iter().flatten().collect();
+        PyArray1::from_vec(py, edges).reshape([self.0.edge_count(), 2])
+    }
+
+    fn labels<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u16>> {
+        // move of each edge, in the order of edges()
+        PyArray1::from_slice(py, self.0.edge_labels())
+    }
+
+    fn move_names(&self) -> Vec<String> {
+        // name of each label, empty for the moves going out of the board
+        let pyra = Pyramide::new(self.0.rules().lines, self.0.rules().diag_allowed);
+        self.0.coups_slice().iter().map(|coup| pyra.coup_name(coup).unwrap_or_default()).collect()
+    }
+
+    fn layer_stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        // dict of arrays indexed by the number of pegs
+        let stats = self.0.layer_stats();
+        let column = |f: fn(&crate::dyn_pyra::state_graph::LayerStats) -> usize| {
+            PyArray1::from_vec(py, stats.iter().map(|layer| f(layer) as u64).collect())
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("pegs", column(|layer| layer.pegs))?;
+        dict.set_item("nodes", column(|layer| layer.nodes))?;
+        dict.set_item("edges", column(|layer| layer.edges))?;
+        dict.set_item("dead_ends", column(|layer| layer.dead_ends))?;
+        Ok(dict)
+    }
+}
+
+#[pymodule]
+fn triangle_problem(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRules>()?;
+    m.add_class::<PyPyramide>()?;
+    m.add_class::<PyStateGraph>()?;
+    Ok(())
+}
@@ -0,0 +1,166 @@
+// HTTP/JSON API over dyn_pyra, for the tools that do not link Rust.
+// Every endpoint is a GET, the position is given by the rules and its seed :
+//   /moves?lines=4&diag=true&seed=65534          legal moves of the position
+//   /apply?lines=4&diag=true&seed=65534&move=c4-a4   position after the move
+//   /solve?lines=4&diag=true&seed=65534          solvability and a solution
+//   /stats?lines=4&diag=true                     nodes, edges and layer sizes of the state graph
+// Seeds are decimal strings in the JSON, a seed of more than 53 bits does not fit a JS number.
+
+use std::collections::HashMap;
+
+use petgraph::algo::connected_components;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::dyn_pyra::solver::Solver;
+use crate::dyn_pyra::state_graph::StateGraph;
+use crate::dyn_pyra::{cherche_coups_possibles, coups_theoriques, Pyramide, PyramideRules, Seed};
+
+// a seed holds at most 128 cells
+const MAX_LINES: usize = 11;
+// requests are handled one at a time, a lost 5 lines position takes the whole state space
+const MAX_SOLVE_LINES: usize = 4;
+const MAX_STATS_LINES: usize = 4;
+
+pub fn serve(server: Server) {
+    // returns when the listener stops, a client gone before its response is only logged
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    for request in server.incoming_requests() {
+        let (status, body) = if *request.method() == Method::Get {
+            handle(request.url())
+        } else {
+            (405, json!({ "error": "only GET is supported" }))
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            eprintln!("Response not sent : {}", err);
+        }
+    }
+}
+
+pub fn handle(url: &str) -> (u16, Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params: HashMap<&str, &str> = query.split('&')
+        .filter_map(|param| param.split_once('='))
+        .collect();
+    let res = match path {
+        "/moves" => position(&params).map(|pyra| position_json(&pyra)),
+        "/apply" => apply(&params),
+        "/solve" => solve(&params),
+        "/stats" => stats(&params),
+        _ => return (404, json!({ "error": format!("unknown endpoint {}", path) })),
+    };
+    match res {
+        Ok(body) => (200, body),
+        Err(message) => (400, json!({ "error": message })),
+    }
+}
+
+fn param<'a>(params: &HashMap<&str, &'a str>, name: &str) -> Result<&'a str, String> {
+    params.get(name).copied().ok_or(format!("missing parameter {}", name))
+}
+
+fn rules(params: &HashMap<&str, &str>) -> Result<PyramideRules, String> {
+    let lines: usize = param(params, "lines")?.parse().map_err(|_| "lines is not a number".to_string())?;
+    if !(1..=MAX_LINES).contains(&lines) {
+        return Err(format!("lines must be between 1 and {}", MAX_LINES))
+    }
+    let diag_allowed = param(params, "diag")?.parse().map_err(|_| "diag must be true or false".to_string())?;
+    Ok(PyramideRules { lines, diag_allowed })
+}
+
+fn position(params: &HashMap<&str, &str>) -> Result<Pyramide, String> {
+    let rules = rules(params)?;
+    let seed: Seed = param(params, "seed")?.parse().map_err(|_| "seed is not a number".to_string())?;
+    let cells_num = rules.lines * rules.lines;
+    if cells_num < Seed::BITS as usize && seed >> cells_num != 0 {
+        return Err(format!("seed has more than {} cells", cells_num))
+    }
+    Ok(Pyramide::init_from_seed(rules.lines, seed, rules.diag_allowed))
+}
+
+fn position_json(pyra: &Pyramide) -> Value {
+    let moves: Vec<String> = cherche_coups_possibles(pyra, &coups_theoriques(pyra))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|coup| pyra.coup_name(coup))
+        .collect();
+    json!({
+        "seed": pyra.seed().to_string(),
+        "pegs": pyra.seed().count_ones(),
+        "moves": moves,
+        "won": pyra.partie_gagne(),
+    })
+}
+
+fn apply(params: &HashMap<&str, &str>) -> Result<Value, String> {
+    let mut pyra = position(params)?;
+    let name = param(params, "move")?;
+    let coup = pyra.coup_from_name(name).ok_or(format!("unknown move {}", name))?;
+    pyra.coup(&coup).map_err(|_| format!("illegal move {}", name))?;
+    Ok(position_json(&pyra))
+}
+
+fn solve(params: &HashMap<&str, &str>) -> Result<Value, String> {
+    let pyra = position(params)?;
+    if pyra.rules().lines > MAX_SOLVE_LINES {
+        return Err(format!("solving is limited to {} lines", MAX_SOLVE_LINES))
+    }
+    let mut solver = Solver::new(&pyra, None);
+    let solution: Option<Vec<String>> = solver.solve(&pyra)
+        .map(|coups| coups.iter().filter_map(|coup| pyra.coup_name(coup)).collect());
+    Ok(json!({
+        "solvable": solution.is_some(),
+        "solution": solution,
+        "explored": solver.explored,
+    }))
+}
+
+fn stats(params: &HashMap<&str, &str>) -> Result<Value, String> {
+    let rules = rules(params)?;
+    if rules.lines > MAX_STATS_LINES {
+        return Err(format!("graph stats are limited to {} lines", MAX_STATS_LINES))
+    }
+    let graph = StateGraph::build_full(rules);
+    Ok(json!({
+        "nodes": graph.node_count(),
+        "edges": graph.edge_count(),
+        "components": connected_components(&graph),
+        "layers": graph.layer_sizes(),
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle() {
+        let (status, body) = handle("/moves?lines=4&diag=false&seed=65534");
+        assert_eq!(status, 200);
+        assert_eq!(body["moves"], json!(["c4-a4"]));
+        assert_eq!(body["pegs"], 15);
+
+        let (status, body) = handle("/apply?lines=4&diag=false&seed=65534&move=c4-a4");
+        assert_eq!(status, 200);
+        assert_eq!(body["seed"], (0xFFFF_u32 & !(1 << 2) & !(1 << 6)).to_string());
+
+        let (status, body) = handle("/solve?lines=4&diag=true&seed=65534");
+        assert_eq!(status, 200);
+        assert_eq!(body["solvable"], true);
+        assert_eq!(body["solution"].as_array().map(|s| s.len()), Some(14));
+        assert_eq!(handle("/solve?lines=5&diag=true&seed=32766").0, 400);
+
+        let (status, body) = handle("/stats?lines=3&diag=false");
+        assert_eq!(status, 200);
+        assert_eq!(body["layers"][1], 9);
+
+        assert_eq!(handle("/apply?lines=4&diag=false&seed=65534&move=d1-d3").0, 400);
+        assert_eq!(handle("/moves?lines=3&diag=false&seed=1024").0, 400);
+        assert_eq!(handle("/moves?lines=3&seed=12").0, 400);
+        assert_eq!(handle("/unknown").0, 404);
+    }
+}
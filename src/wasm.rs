@@ -0,0 +1,133 @@
+// Browser bindings over dyn_pyra, used by the page in www/ (built with www/build.sh).
+// Moves are exchanged by name ("c4-a4"), cells by index (0 is the top cell).
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::dyn_pyra::solver::Solver;
+use crate::dyn_pyra::{cherche_coups_possibles, coups_theoriques, Coup, Pyramide, Seed};
+
+// a seed holds at most 128 cells
+const MAX_LINES: usize = 11;
+// hints come from a full search on the page thread, a lost 5 lines position freezes it
+const MAX_HINT_LINES: usize = 4;
+
+#[wasm_bindgen]
+pub struct Game {
+    pyra: Pyramide,
+    coups: Vec<Coup>,
+    // moves played, for undo
+    history: Vec<Coup>,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new(lines: usize, diag_allowed: bool, hole: usize) -> Result<Game, String> {
+        // full board but the starting hole
+        if !(2..=MAX_LINES).contains(&lines) {
+            return Err(format!("lines must be between 2 and {}", MAX_LINES))
+        }
+        let cells_num = lines * lines;
+        if hole >= cells_num {
+            return Err(format!("hole must be a cell index below {}", cells_num))
+        }
+        let full_seed: Seed = Seed::MAX >> (Seed::BITS as usize - cells_num);
+        let pyra = Pyramide::init_from_seed(lines, full_seed & !(1 << hole), diag_allowed);
+        let coups = coups_theoriques(&pyra);
+        Ok(Game { pyra, coups, history: vec![] })
+    }
+
+    pub fn lines(&self) -> usize {
+        self.pyra.rules().lines
+    }
+
+    pub fn cells(&self) -> Vec<u8> {
+        // 1 for a peg, 0 for a hole, row by row
+        self.pyra.iter().map(|cell| (cell == &Some(true)) as u8).collect()
+    }
+
+    pub fn cell_name(&self, idx: usize) -> String {
+        self.pyra.cell_name(idx)
+    }
+
+    pub fn moves(&self) -> Vec<String> {
+        cherche_coups_possibles(&self.pyra, &self.coups)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|coup| self.pyra.coup_name(coup))
+            .collect()
+    }
+
+    pub fn play(&mut self, name: &str) -> bool {
+        // false when the move is unknown or not legal, the board is then unchanged
+        let Some(coup) = self.pyra.coup_from_name(name) else {
+            return false
+        };
+        if self.pyra.coup(&coup).is_err() {
+            return false
+        }
+        self.history.push(coup);
+        return true
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(coup) => {
+                self.pyra.coup_inverse(&coup).expect("move was played");
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.pyra.partie_gagne()
+    }
+
+    pub fn is_over(&self) -> bool {
+        cherche_coups_possibles(&self.pyra, &self.coups).is_none()
+    }
+
+    pub fn hint(&self) -> Result<Option<String>, String> {
+        // first move of a winning sequence, None if the position is lost, an error (thrown on
+        // the page) for a board too large to be searched
+        if self.lines() > MAX_HINT_LINES {
+            return Err(format!("no hint above {} lines", MAX_HINT_LINES))
+        }
+        let solution = Solver::new(&self.pyra, None).solve(&self.pyra);
+        Ok(solution.and_then(|solution| self.pyra.coup_name(solution.first()?)))
+    }
+
+    pub fn board(&self) -> String {
+        self.pyra.display().unicode(true).to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game() {
+        assert!(Game::new(1, false, 0).is_err());
+        assert!(Game::new(4, false, 16).is_err());
+
+        let mut game = Game::new(4, true, 0).expect("valid rules");
+        assert_eq!(game.cells().iter().filter(|cell| **cell == 1).count(), 15);
+        assert_eq!(game.moves().len(), 3);
+        assert!(!game.play("d1-d3"));
+        let hint = game.hint().expect("small board").expect("solvable with diagonals");
+        assert!(game.play(&hint));
+        assert_eq!(game.cells()[0], 1);
+        assert!(game.undo());
+        assert!(!game.undo());
+        assert_eq!(game.cells()[0], 0);
+        assert!(!game.is_won() && !game.is_over());
+
+        let game = Game::new(4, false, 0).expect("valid rules");
+        assert_eq!(game.hint(), Ok(None));
+        let game = Game::new(5, true, 0).expect("valid rules");
+        assert!(game.hint().is_err());
+    }
+}
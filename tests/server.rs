@@ -0,0 +1,51 @@
+#![cfg(feature = "server")]
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+
+use tiny_http::Server;
+
+use triangle_problem::server::serve;
+
+fn get(addr: &str, url: &str) -> (u16, serde_json::Value) {
+    let mut stream = TcpStream::connect(addr).expect("server listening");
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", url, addr).expect("request sent");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("response read");
+    let (head, body) = response.split_once("\r\n\r\n").expect("http response");
+    let status = head.split_whitespace().nth(1).and_then(|s| s.parse().ok()).expect("status code");
+    assert!(head.contains("application/json"));
+    (status, serde_json::from_str(body).expect("json body"))
+}
+
+#[test]
+fn test_server_on_localhost() {
+    let server = Server::http("127.0.0.1:0").expect("free port");
+    let addr = server.server_addr().to_ip().expect("ip address").to_string();
+    thread::spawn(move || serve(server));
+
+    let (status, body) = get(&addr, "/moves?lines=4&diag=true&seed=65534");
+    assert_eq!(status, 200);
+    assert_eq!(body["moves"].as_array().map(|moves| moves.len()), Some(3));
+
+    let (status, body) = get(&addr, "/apply?lines=4&diag=true&seed=65534&move=c4-a4");
+    assert_eq!(status, 200);
+    assert_eq!(body["pegs"], 14);
+
+    // a client leaving before its response does not stop the server
+    let mut stream = TcpStream::connect(&addr).expect("server listening");
+    write!(stream, "GET /stats?lines=4&diag=true HTTP/1.0\r\nHost: {}\r\n\r\n", addr).expect("request sent");
+    drop(stream);
+    let (status, _) = get(&addr, "/moves?lines=3&diag=false&seed=510");
+    assert_eq!(status, 200);
+
+    let (status, body) = get(&addr, "/solve?lines=3&diag=false&seed=510");
+    assert_eq!(status, 200);
+    assert_eq!(body["solvable"], false);
+    assert!(body["solution"].is_null());
+
+    let (status, body) = get(&addr, "/stats?lines=5&diag=true");
+    assert_eq!(status, 400);
+    assert!(body["error"].is_string());
+}
@@ -0,0 +1,11 @@
+#!/bin/sh
+# Builds the browser version in www/pkg, then serve www/ with any static server :
+#   python3 -m http.server -d www
+# Needs once : rustup target add wasm32-unknown-unknown
+#              cargo install wasm-bindgen-cli --version 0.2.100
+# The crates come from the local cargo cache, no network is needed after that.
+set -e
+cd "$(dirname "$0")/.."
+cargo build --offline --release --lib --target wasm32-unknown-unknown --features wasm
+wasm-bindgen --target web --no-typescript --out-dir www/pkg \
+    target/wasm32-unknown-unknown/release/triangle_problem.wasm
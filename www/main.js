@@ -0,0 +1,82 @@
+import init, { Game } from "./pkg/triangle_problem.js";
+
+let game = null;
+let selected = null;
+
+const board = document.getElementById("board");
+const status = document.getElementById("status");
+
+function lines() {
+  return Number(document.getElementById("lines").value);
+}
+
+function diag() {
+  return document.getElementById("diag").checked;
+}
+
+function targets(from) {
+  // arrival cells of the legal moves starting from the cell `from`
+  if (game === null || from === null) {
+    return [];
+  }
+  const prefix = game.cell_name(from) + "-";
+  return game.moves().filter((name) => name.startsWith(prefix)).map((name) => name.slice(prefix.length));
+}
+
+function render() {
+  board.replaceChildren();
+  const n = game === null ? lines() : game.lines();
+  const cells = game === null ? new Uint8Array(n * n).fill(1) : game.cells();
+  const arrivals = targets(selected);
+  for (let row = 0; row < n; row++) {
+    const div = document.createElement("div");
+    div.className = "row";
+    for (let idx = row * row; idx < (row + 1) * (row + 1); idx++) {
+      const cell = document.createElement("button");
+      cell.className = "cell";
+      if (cells[idx] === 1) cell.classList.add("peg");
+      if (idx === selected) cell.classList.add("selected");
+      if (game !== null && arrivals.includes(game.cell_name(idx))) cell.classList.add("target");
+      cell.onclick = () => click(idx);
+      div.appendChild(cell);
+    }
+    board.appendChild(div);
+  }
+  if (game === null) {
+    status.textContent = "Choisir la case vide de départ";
+  } else if (game.is_won()) {
+    status.textContent = "Gagné !";
+  } else if (game.is_over()) {
+    status.textContent = "Plus aucun coup possible";
+  } else {
+    status.textContent = game.moves().length + " coups possibles";
+  }
+}
+
+function click(idx) {
+  if (game === null) {
+    game = new Game(lines(), diag(), idx);
+  } else if (selected !== null && targets(selected).includes(game.cell_name(idx))) {
+    game.play(game.cell_name(selected) + "-" + game.cell_name(idx));
+    selected = null;
+  } else {
+    selected = game.cells()[idx] === 1 ? idx : null;
+  }
+  render();
+}
+
+await init();
+
+document.getElementById("new").onclick = () => { game = null; selected = null; render(); };
+document.getElementById("undo").onclick = () => { if (game !== null) game.undo(); selected = null; render(); };
+document.getElementById("hint").onclick = () => {
+  if (game === null) return;
+  try {
+    const hint = game.hint();
+    status.textContent = hint === undefined || hint === null ? "Pas d'indice, la position est perdue" : "Indice : " + hint;
+  } catch (error) {
+    status.textContent = "Pas d'indice sur un plateau de plus de 4 lignes";
+  }
+};
+
+render();